@@ -0,0 +1,352 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use bevy::{
+    asset::{AssetLoader, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ghost::{is_rewinding, BestGhost, Ghost, GoalReached, Rewind},
+    interaction::{InteractionSet, PlayerContact, PlayerInteraction},
+    player::{Player, RealPlayer},
+    score::PlayerDied,
+};
+
+const PROGRESS_VERSION: u8 = 0;
+// seconds an unlock toast stays up
+const TOAST_TIME: f32 = 3.;
+
+pub struct AchievementPlugin;
+
+impl Plugin for AchievementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<AchievementList>()
+            .add_asset_loader(AchievementLoader)
+            .add_event::<StatEvent>()
+            .add_event::<AchievementUnlocked>()
+            .init_resource::<AchievementProgress>()
+            .add_systems(Startup, load_achievements)
+            .add_systems(
+                Update,
+                (
+                    (
+                        collect_stat.in_set(InteractionSet::Handle),
+                        death_stat,
+                        ghost_stat,
+                        goal_stat,
+                        switch_stat,
+                    ),
+                    track_progress,
+                    spawn_toast,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, update_toasts);
+    }
+}
+
+// gameplay counters achievements are measured against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Stat {
+    FruitCollected,
+    Deaths,
+    // most ghosts alive at once
+    GhostCount,
+    LevelsCompleted,
+    CharacterSwitches,
+}
+
+impl Stat {
+    // peaks keep the highest value seen, everything else adds up
+    fn is_peak(&self) -> bool {
+        matches!(self, Stat::GhostCount)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StatEvent {
+    pub stat: Stat,
+    pub value: usize,
+}
+
+impl StatEvent {
+    pub fn add(stat: Stat) -> StatEvent {
+        StatEvent { stat, value: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AchievementDef {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub stat: Stat,
+    pub goal: usize,
+}
+
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "0f6b3a4e-2a7c-4a5e-9d51-8f1c6e2b7d40"]
+pub struct AchievementList(pub Vec<AchievementDef>);
+
+#[derive(Default)]
+struct AchievementLoader;
+
+impl AssetLoader for AchievementLoader {
+    fn extensions(&self) -> &[&str] {
+        &["ach.ron"]
+    }
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let list: Vec<AchievementDef> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(AchievementList(list)));
+            Ok(())
+        })
+    }
+}
+
+#[derive(Resource)]
+pub struct Achievements(pub Handle<AchievementList>);
+
+#[derive(Event)]
+pub struct AchievementUnlocked(pub AchievementDef);
+
+// saved as part of the profile
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct AchievementProgress {
+    version: u8,
+    stats: HashMap<Stat, usize>,
+    unlocked: HashSet<String>,
+}
+
+impl Default for AchievementProgress {
+    fn default() -> Self {
+        AchievementProgress {
+            version: PROGRESS_VERSION,
+            stats: HashMap::new(),
+            unlocked: HashSet::new(),
+        }
+    }
+}
+
+impl AchievementProgress {
+    pub fn load(path: &Path) -> Result<AchievementProgress, anyhow::Error> {
+        let progress: AchievementProgress = ron::from_str(&std::fs::read_to_string(path)?)?;
+        match progress.version {
+            0 => Ok(progress),
+            v => Err(anyhow::anyhow!("Unsuported achievement version: {}", v)),
+        }
+    }
+
+    pub fn load_or_default(path: &Path) -> AchievementProgress {
+        if !path.exists() {
+            return AchievementProgress::default();
+        }
+        AchievementProgress::load(path).unwrap_or_else(|e| {
+            error!("Failed to load achievements: {}", e);
+            AchievementProgress::default()
+        })
+    }
+
+    pub fn get(&self, stat: Stat) -> usize {
+        self.stats.get(&stat).copied().unwrap_or_default()
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    // true if the stat moved
+    fn record(&mut self, event: StatEvent) -> bool {
+        let value = self.stats.entry(event.stat).or_default();
+        let old = *value;
+        if event.stat.is_peak() {
+            *value = old.max(event.value);
+        } else {
+            *value += event.value;
+        }
+        *value != old
+    }
+
+    // marks every reached achievement as unlocked and returns the new ones
+    fn unlock<'a>(&mut self, defs: &'a [AchievementDef]) -> Vec<&'a AchievementDef> {
+        let mut new = Vec::new();
+        for def in defs {
+            if self.get(def.stat) >= def.goal && self.unlocked.insert(def.id.clone()) {
+                new.push(def);
+            }
+        }
+        new
+    }
+}
+
+fn load_achievements(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Achievements(asset_server.load("achievements.ach.ron")));
+}
+
+fn collect_stat(mut contacts: EventReader<PlayerContact>, mut stats: EventWriter<StatEvent>) {
+    for contact in contacts.iter() {
+        if contact.began(PlayerInteraction::Collect) {
+            stats.send(StatEvent::add(Stat::FruitCollected));
+        }
+    }
+}
+
+fn death_stat(mut died: EventReader<PlayerDied>, mut stats: EventWriter<StatEvent>) {
+    for _ in died.iter() {
+        stats.send(StatEvent::add(Stat::Deaths));
+    }
+}
+
+fn ghost_stat(
+    ghosts: Query<(), (With<Ghost>, Without<BestGhost>)>,
+    mut stats: EventWriter<StatEvent>,
+    mut last: Local<usize>,
+) {
+    let count = ghosts.iter().count();
+    if count != *last {
+        *last = count;
+        stats.send(StatEvent {
+            stat: Stat::GhostCount,
+            value: count,
+        });
+    }
+}
+
+fn goal_stat(mut goal: EventReader<GoalReached>, mut stats: EventWriter<StatEvent>) {
+    for _ in goal.iter() {
+        stats.send(StatEvent::add(Stat::LevelsCompleted));
+    }
+}
+
+fn switch_stat(
+    player: Query<&Player, With<RealPlayer>>,
+    mut stats: EventWriter<StatEvent>,
+    rewind: Res<Rewind>,
+    mut last: Local<Option<Player>>,
+) {
+    let Ok(player) = player.get_single() else {return;};
+    // rewinding hands back old characters, that is not a switch
+    if !is_rewinding(rewind) && last.map_or(false, |last| last != *player) {
+        stats.send(StatEvent::add(Stat::CharacterSwitches));
+    }
+    *last = Some(*player);
+}
+
+fn track_progress(
+    mut stats: EventReader<StatEvent>,
+    mut progress: ResMut<AchievementProgress>,
+    achievements: Res<Achievements>,
+    lists: Res<Assets<AchievementList>>,
+    mut unlocked: EventWriter<AchievementUnlocked>,
+) {
+    let mut changed = false;
+    for event in stats.iter() {
+        changed |= progress.record(*event);
+    }
+    if !changed {
+        return;
+    }
+    if let Some(list) = lists.get(&achievements.0) {
+        for def in progress.unlock(&list.0) {
+            info!("Achievement unlocked: {}", def.name);
+            unlocked.send(AchievementUnlocked(def.clone()));
+        }
+    }
+}
+
+#[derive(Component)]
+struct Toast(Timer);
+
+fn spawn_toast(
+    mut commands: Commands,
+    mut unlocked: EventReader<AchievementUnlocked>,
+    asset_server: Res<AssetServer>,
+    toasts: Query<(), With<Toast>>,
+) {
+    // stack new toasts under any still showing
+    let mut index = toasts.iter().count();
+    for AchievementUnlocked(def) in unlocked.iter() {
+        commands.spawn((
+            TextBundle::from_section(
+                format!("Achievement: {}\n{}", def.name, def.description),
+                TextStyle {
+                    font: asset_server.load("Raleway-Regular.ttf"),
+                    font_size: 24.,
+                    color: Color::GOLD,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10. + 60. * index as f32),
+                left: Val::Px(10.),
+                ..Default::default()
+            }),
+            Toast(Timer::from_seconds(TOAST_TIME, TimerMode::Once)),
+            Name::new("Achievement Toast"),
+        ));
+        index += 1;
+    }
+}
+
+fn update_toasts(
+    mut commands: Commands,
+    mut toasts: Query<(Entity, &mut Toast, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut toast, mut text) in &mut toasts {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        // fade out over the last second
+        let alpha = toast.0.remaining_secs().min(1.);
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+#[cfg(test)]
+fn def(id: &str, stat: Stat, goal: usize) -> AchievementDef {
+    AchievementDef {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: String::new(),
+        stat,
+        goal,
+    }
+}
+
+#[test]
+fn achievements_unlock_once() {
+    let defs = vec![
+        def("fruit", Stat::FruitCollected, 2),
+        def("crowd", Stat::GhostCount, 3),
+    ];
+    let mut progress = AchievementProgress::default();
+    assert!(progress.record(StatEvent::add(Stat::FruitCollected)));
+    assert!(progress.unlock(&defs).is_empty());
+    progress.record(StatEvent::add(Stat::FruitCollected));
+    progress.record(StatEvent {
+        stat: Stat::GhostCount,
+        value: 3,
+    });
+    assert!(!progress.record(StatEvent {
+        stat: Stat::GhostCount,
+        value: 1,
+    }));
+    assert_eq!(progress.get(Stat::GhostCount), 3);
+    let new: Vec<_> = progress.unlock(&defs).iter().map(|def| def.id.clone()).collect();
+    assert_eq!(new, vec!["fruit", "crowd"]);
+    assert!(progress.unlock(&defs).is_empty());
+    assert!(progress.is_unlocked("crowd"));
+}
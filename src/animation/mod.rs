@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use bevy::prelude::*;
 use bevy::reflect::{TypePath, TypeUuid};
 
+use super::pause::is_paused;
 use super::player::*;
 use super::*;
 
@@ -12,7 +13,7 @@ pub struct PhoxAnimationPlugin;
 
 impl Plugin for PhoxAnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(animate_sprite)
+        app.add_system(animate_sprite.run_if(not(is_paused)))
             .add_systems(Update, change_player_animation)
             .add_systems(Update, update_animation_components)
             .add_systems(Last, add_frame_time)
@@ -46,19 +47,42 @@ impl FrameTime {
     }
 }
 
+// despawned after playing through once instead of looping
+#[derive(Component)]
+pub struct OneShot;
+
+// switches to this animation after playing through once
+#[derive(Component)]
+pub struct ThenPlay(pub Handle<SpriteAnimation>);
+
 fn animate_sprite(
+    mut commands: Commands,
     mut entities: Query<(
+        Entity,
         &mut TextureAtlasSprite,
         &Handle<SpriteAnimation>,
         &mut FrameTime,
+        Option<&OneShot>,
+        Option<&ThenPlay>,
     )>,
     animations: Res<Assets<SpriteAnimation>>,
     time: Res<Time>,
 ) {
-    for (mut sprite, animation, mut frame_time) in entities.iter_mut() {
+    for (entity, mut sprite, animation, mut frame_time, one_shot, then_play) in entities.iter_mut() {
         let Some(animation) = animations.get(animation) else {error!("Animation Not Loaded"); continue;};
         frame_time.tick(time.delta());
         sprite.index += frame_time.frames();
+        if one_shot.is_some() && sprite.index >= animation.len {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let Some(ThenPlay(next)) = then_play {
+            if sprite.index >= animation.len {
+                sprite.index = 0;
+                commands.entity(entity).insert(next.clone()).remove::<ThenPlay>();
+                continue;
+            }
+        }
         sprite.index %= animation.len;
     }
 }
@@ -174,6 +198,64 @@ impl FromWorld for Animations {
             asset_server.load("Animations/Collectables.san.ron#Bananas"),
         );
 
+        // Effects
+        map.add_animation(
+            Animation::Desappearing,
+            asset_server.load("Animations/Effects.san.ron#Desappearing"),
+        );
+        map.add_animation(
+            Animation::Appearing,
+            asset_server.load("Animations/Effects.san.ron#Appearing"),
+        );
+
+        // Checkpoints
+        map.add_animation(
+            Animation::CheckpointNoFlag,
+            asset_server.load("Animations/Checkpoints.san.ron#NoFlag"),
+        );
+        map.add_animation(
+            Animation::CheckpointFlagOut,
+            asset_server.load("Animations/Checkpoints.san.ron#FlagOut"),
+        );
+        map.add_animation(
+            Animation::CheckpointFlagIdle,
+            asset_server.load("Animations/Checkpoints.san.ron#FlagIdle"),
+        );
+        map.add_animation(
+            Animation::EndIdle,
+            asset_server.load("Animations/Checkpoints.san.ron#EndIdle"),
+        );
+        map.add_animation(
+            Animation::EndPressed,
+            asset_server.load("Animations/Checkpoints.san.ron#EndPressed"),
+        );
+
+        // Traps
+        map.add_animation(
+            Animation::Spikes,
+            asset_server.load("Animations/Traps.san.ron#Spikes"),
+        );
+        map.add_animation(
+            Animation::Saw,
+            asset_server.load("Animations/Traps.san.ron#Saw"),
+        );
+        map.add_animation(
+            Animation::PlatformOn,
+            asset_server.load("Animations/Traps.san.ron#PlatformOn"),
+        );
+        map.add_animation(
+            Animation::PlatformOff,
+            asset_server.load("Animations/Traps.san.ron#PlatformOff"),
+        );
+        map.add_animation(
+            Animation::FallingOn,
+            asset_server.load("Animations/Traps.san.ron#FallingOn"),
+        );
+        map.add_animation(
+            Animation::FallingOff,
+            asset_server.load("Animations/Traps.san.ron#FallingOff"),
+        );
+
         //terrain
         map.add_atlas(
             Animation::Terrain,
@@ -223,6 +305,19 @@ pub enum Animation {
     GuyJump,
     GuyDoubleJump,
     GuyFall,
+    Desappearing,
+    Appearing,
+    EndIdle,
+    EndPressed,
+    CheckpointNoFlag,
+    CheckpointFlagOut,
+    CheckpointFlagIdle,
+    Spikes,
+    Saw,
+    PlatformOn,
+    PlatformOff,
+    FallingOn,
+    FallingOff,
     Terrain,
 }
 
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadedAsset},
+    audio::{AudioSinkPlayback, Volume},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ghost::{BestGhost, Ghost},
+    interaction::{InteractionSet, PlayerContact, PlayerInteraction},
+    map::{Level, LevelHash, LoadedLevel},
+    player::PlayerMovement,
+    score::PlayerDied,
+    settings::GameSettings,
+};
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(CuePlugin)
+            .add_asset::<SoundBank>()
+            .add_asset_loader(SoundBankLoader)
+            .add_systems(Startup, load_sounds)
+            .add_systems(Update, (play_cues, level_music))
+            .add_systems(
+                Update,
+                music_volume.run_if(resource_changed::<GameSettings>()),
+            );
+    }
+}
+
+// turns gameplay events into cues, apart from playback so it can run without audio
+pub struct CuePlugin;
+
+impl Plugin for CuePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayCue>().add_systems(
+            Update,
+            (
+                movement_cue,
+                collect_cue.in_set(InteractionSet::Handle),
+                ghost_cue,
+                death_cue,
+            ),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Cue {
+    Jump,
+    DoubleJump,
+    Land,
+    Collect,
+    GhostSpawn,
+    Death,
+    MenuClick,
+}
+
+// each has its own volume in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SoundCategory {
+    Music,
+    Effects,
+    Ui,
+}
+
+impl Cue {
+    pub fn category(self) -> SoundCategory {
+        match self {
+            Cue::MenuClick => SoundCategory::Ui,
+            _ => SoundCategory::Effects,
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct PlayCue(pub Cue);
+
+#[derive(Deserialize)]
+struct CueFile {
+    sounds: Vec<String>,
+    #[serde(default)]
+    pitch: f32,
+}
+
+struct CueSounds {
+    // one is picked at random each time the cue plays
+    sounds: Vec<Handle<AudioSource>>,
+    // how far the playback speed can stray either way
+    pitch: f32,
+}
+
+#[derive(TypeUuid, TypePath)]
+#[uuid = "9d6e2b47-0c3a-4f85-b1e8-7a5c93d2f016"]
+pub struct SoundBank(HashMap<Cue, CueSounds>);
+
+#[derive(Default)]
+pub struct SoundBankLoader;
+
+impl AssetLoader for SoundBankLoader {
+    fn extensions(&self) -> &[&str] {
+        &["bank.ron"]
+    }
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file: HashMap<Cue, CueFile> = ron::de::from_bytes(bytes)?;
+            let mut paths = Vec::new();
+            let mut bank = HashMap::new();
+            for (cue, entry) in file {
+                let mut sounds = Vec::new();
+                for path in entry.sounds {
+                    let path = AssetPath::from(path.as_str()).to_owned();
+                    sounds.push(load_context.get_handle(path.clone()));
+                    paths.push(path);
+                }
+                bank.insert(
+                    cue,
+                    CueSounds {
+                        sounds,
+                        pitch: entry.pitch.abs(),
+                    },
+                );
+            }
+            load_context.set_default_asset(LoadedAsset::new(SoundBank(bank)).with_dependencies(paths));
+            Ok(())
+        })
+    }
+}
+
+#[derive(Resource)]
+pub struct Sounds(pub Handle<SoundBank>);
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Sounds(asset_server.load("sounds.bank.ron")));
+}
+
+fn movement_cue(mut movement: EventReader<PlayerMovement>, mut cues: EventWriter<PlayCue>) {
+    for movement in movement.iter() {
+        cues.send(PlayCue(match movement {
+            PlayerMovement::Jump => Cue::Jump,
+            PlayerMovement::DoubleJump => Cue::DoubleJump,
+            PlayerMovement::Land => Cue::Land,
+        }));
+    }
+}
+
+fn collect_cue(mut contacts: EventReader<PlayerContact>, mut cues: EventWriter<PlayCue>) {
+    for contact in contacts.iter() {
+        if contact.began(PlayerInteraction::Collect) {
+            cues.send(PlayCue(Cue::Collect));
+        }
+    }
+}
+
+fn ghost_cue(
+    ghosts: Query<(), (Added<Ghost>, Without<BestGhost>)>,
+    mut cues: EventWriter<PlayCue>,
+) {
+    for _ in &ghosts {
+        cues.send(PlayCue(Cue::GhostSpawn));
+    }
+}
+
+fn death_cue(mut died: EventReader<PlayerDied>, mut cues: EventWriter<PlayCue>) {
+    for _ in died.iter() {
+        cues.send(PlayCue(Cue::Death));
+    }
+}
+
+fn play_cues(
+    mut commands: Commands,
+    mut cues: EventReader<PlayCue>,
+    sounds: Res<Sounds>,
+    banks: Res<Assets<SoundBank>>,
+    settings: Res<GameSettings>,
+) {
+    let Some(bank) = banks.get(&sounds.0) else {cues.clear(); return;};
+    let mut rng = rand::thread_rng();
+    for PlayCue(cue) in cues.iter() {
+        let Some(entry) = bank.0.get(cue) else {continue;};
+        if entry.sounds.is_empty() {
+            continue;
+        }
+        let sound = entry.sounds[rng.gen_range(0..entry.sounds.len())].clone();
+        let speed = 1. + rng.gen_range(-entry.pitch..=entry.pitch);
+        commands.spawn((
+            AudioBundle {
+                source: sound,
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new_relative(settings.volume(cue.category())))
+                    .with_speed(speed),
+            },
+            Name::new(format!("{:?} Sound", cue)),
+        ));
+    }
+}
+
+#[derive(Component)]
+struct Music(String);
+
+// swaps the track when a level with different music is loaded
+fn level_music(
+    mut commands: Commands,
+    level_hash: Res<LevelHash>,
+    loaded_level: Res<LoadedLevel>,
+    levels: Res<Assets<Level>>,
+    music: Query<(Entity, &Music)>,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+) {
+    if !level_hash.is_changed() {
+        return;
+    }
+    let track = levels
+        .get(&loaded_level.0)
+        .and_then(|level| level.meta.music.clone());
+    if music.iter().any(|(_, playing)| Some(&playing.0) == track.as_ref()) {
+        return;
+    }
+    for (entity, _) in &music {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(track) = track else {return;};
+    commands.spawn((
+        AudioBundle {
+            source: asset_server.load(track.as_str()),
+            settings: PlaybackSettings::LOOP
+                .with_volume(Volume::new_relative(settings.volume(SoundCategory::Music))),
+        },
+        Music(track),
+        Name::new("Music"),
+    ));
+}
+
+fn music_volume(settings: Res<GameSettings>, music: Query<&AudioSink, With<Music>>) {
+    for sink in &music {
+        sink.set_volume(settings.volume(SoundCategory::Music));
+    }
+}
+
+#[test]
+fn gameplay_events_trigger_cues() {
+    use crate::interaction::ContactPhase;
+
+    let mut app = App::new();
+    app.add_event::<PlayerMovement>()
+        .add_event::<PlayerContact>()
+        .add_event::<PlayerDied>()
+        .add_plugins(CuePlugin);
+    let object = app.world.spawn_empty().id();
+    app.world.send_event(PlayerMovement::Jump);
+    app.world.send_event(PlayerMovement::Land);
+    app.world.send_event(PlayerDied { score: 0 });
+    app.world.send_event(PlayerContact {
+        player: object,
+        object,
+        interaction: PlayerInteraction::Collect,
+        phase: ContactPhase::Begin,
+    });
+    // ending a contact is not a pickup
+    app.world.send_event(PlayerContact {
+        player: object,
+        object,
+        interaction: PlayerInteraction::Collect,
+        phase: ContactPhase::End,
+    });
+    app.update();
+
+    let mut cues: Vec<Cue> = app
+        .world
+        .resource::<Events<PlayCue>>()
+        .iter_current_update_events()
+        .map(|PlayCue(cue)| *cue)
+        .collect();
+    cues.sort_by_key(|cue| *cue as u8);
+    assert_eq!(cues, vec![Cue::Jump, Cue::Land, Cue::Collect, Cue::Death]);
+}
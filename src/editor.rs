@@ -1,6 +1,6 @@
 use crate::{
     animation::{Animation, Animations},
-    map::{MapObject, MapItem},
+    map::{FallingPlatform, MapObject, MapItem, MovingPlatform, Saw, Spikes},
     GameState, MainCam,
 };
 use belly::prelude::*;
@@ -38,10 +38,22 @@ fn setup_editor(mut commands: Commands) {
         .spawn(NodeBundle::default())
         .with_children(|p| {
             childern.push(p.spawn(NodeBundle::default()).id());
+            childern.push(p.spawn(NodeBundle::default()).id());
+            childern.push(p.spawn(NodeBundle::default()).id());
+            childern.push(p.spawn(NodeBundle::default()).id());
+            childern.push(p.spawn(NodeBundle::default()).id());
         })
         .id();
     let next = childern.pop().expect("Exnugh childen for all objs");
     commands.add(crate::map::Square::ui_draw(next));
+    let next = childern.pop().expect("Exnugh childen for all objs");
+    commands.add(Spikes::ui_draw(next));
+    let next = childern.pop().expect("Exnugh childen for all objs");
+    commands.add(Saw::ui_draw(next));
+    let next = childern.pop().expect("Exnugh childen for all objs");
+    commands.add(MovingPlatform::ui_draw(next));
+    let next = childern.pop().expect("Exnugh childen for all objs");
+    commands.add(FallingPlatform::ui_draw(next));
     commands.add(eml!(
         <body>
         <div c:level_editor {editor}>
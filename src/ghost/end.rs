@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use super::{
+    Ghost, GhostHistory, GhostRules, GhostRun, KinematicGhost, PlayerInputs, SyncOffset,
+    SYNCFRAME,
+};
+use crate::{
+    animation::{Animation, Animations, OneShot},
+    player::{Grounded, RealPlayer},
+    user_input::PlayerInput,
+};
+
+// what a ghost does once it has replayed all of its trail
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GhostEnd {
+    // start the trail again from the beginning
+    Loop,
+    // vanish with the Desappearing effect
+    Despawn,
+    // stay where the trail ended
+    #[default]
+    Freeze,
+    // run after the player
+    Chase,
+}
+
+impl GhostEnd {
+    fn next(self) -> GhostEnd {
+        match self {
+            GhostEnd::Loop => GhostEnd::Despawn,
+            GhostEnd::Despawn => GhostEnd::Freeze,
+            GhostEnd::Freeze => GhostEnd::Chase,
+            GhostEnd::Chase => GhostEnd::Loop,
+        }
+    }
+}
+
+// the ghost is past the end of its trail and no longer replays it
+#[derive(Component)]
+pub struct TrailEnded;
+
+#[derive(Event)]
+pub struct GhostVanished(pub Vec3);
+
+pub(super) fn cycle_end(input: Res<Input<KeyCode>>, mut rules: ResMut<GhostRules>) {
+    if input.just_pressed(KeyCode::F4) {
+        rules.end = rules.end.next();
+        info!("Ghost end: {:?}", rules.end);
+    }
+}
+
+pub(super) fn end_of_trail(
+    mut commands: Commands,
+    mut ghosts: Query<
+        (
+            Entity,
+            &GhostEnd,
+            &mut Ghost,
+            &GhostHistory,
+            &mut Transform,
+            &mut Velocity,
+            Option<&mut KinematicGhost>,
+            Option<&GhostRun>,
+        ),
+        Without<TrailEnded>,
+    >,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+    mut vanished: EventWriter<GhostVanished>,
+) {
+    for (entity, end, mut ghost, history, mut transform, mut velocity, kinematic, run) in
+        &mut ghosts
+    {
+        let (trail_end, offsets) = match run {
+            Some(run) => (run.trail.end(), &run.offsets),
+            None => (inputs.trail.end(), &offsets.0),
+        };
+        let trail_end = history.end.map_or(trail_end, |end| end.min(trail_end));
+        let frame = match &kinematic {
+            Some(kinematic) => history.start + kinematic.frame as usize,
+            None => history.start + ghost.0,
+        };
+        if frame < trail_end {
+            continue;
+        }
+        match end {
+            GhostEnd::Loop => {
+                // restart on the first sync point so drift correction has something to snap to
+                let first = (history.start + SYNCFRAME - 1) / SYNCFRAME * SYNCFRAME;
+                ghost.0 = first - history.start;
+                if let Some(mut kinematic) = kinematic {
+                    kinematic.frame = ghost.0 as f32;
+                }
+                if let Some(position) = offsets.get(first) {
+                    transform.translation = position.extend(transform.translation.z);
+                }
+            }
+            GhostEnd::Despawn => {
+                commands.entity(entity).despawn_recursive();
+                vanished.send(GhostVanished(transform.translation));
+            }
+            GhostEnd::Freeze => {
+                *velocity = Velocity::zero();
+                commands
+                    .entity(entity)
+                    .insert((TrailEnded, RigidBody::Fixed))
+                    .remove::<KinematicGhost>();
+            }
+            GhostEnd::Chase => {
+                commands
+                    .entity(entity)
+                    .insert((TrailEnded, RigidBody::Dynamic))
+                    .remove::<KinematicGhost>();
+            }
+        }
+    }
+}
+
+// presses the ghost's inputs so move_player walks it toward the player
+pub(super) fn chase_player(
+    player: Query<&Transform, With<RealPlayer>>,
+    mut ghosts: Query<
+        (&GhostEnd, &Transform, &Grounded, &mut ActionState<PlayerInput>),
+        (With<TrailEnded>, Without<RealPlayer>),
+    >,
+) {
+    let Ok(player) = player.get_single() else {return;};
+    for (end, transform, grounded, mut state) in &mut ghosts {
+        if *end != GhostEnd::Chase {
+            continue;
+        }
+        let offset = player.translation - transform.translation;
+        state.release(PlayerInput::Left);
+        state.release(PlayerInput::Right);
+        state.release(PlayerInput::Jump);
+        if offset.x < -8. {
+            state.press(PlayerInput::Left);
+        } else if offset.x > 8. {
+            state.press(PlayerInput::Right);
+        }
+        if offset.y > 24. && grounded.0 {
+            state.press(PlayerInput::Jump);
+        }
+    }
+}
+
+pub(super) fn spawn_vanish_effect(
+    mut commands: Commands,
+    mut vanished: EventReader<GhostVanished>,
+    animations: Res<Animations>,
+) {
+    for GhostVanished(position) in vanished.iter() {
+        let Some(handle) = animations.get_animation(Animation::Desappearing) else {error!("Failed to find animation: Desappearing"); continue;};
+        commands.spawn((
+            SpriteSheetBundle {
+                transform: Transform::from_translation(*position + Vec3::Z),
+                ..Default::default()
+            },
+            handle,
+            OneShot,
+            Name::new("Ghost Vanish"),
+        ));
+    }
+}
+
+#[cfg(test)]
+fn ended_ghost(end: GhostEnd) -> (App, Entity) {
+    let mut app = App::new();
+    let mut inputs = PlayerInputs::default();
+    let mut offsets = SyncOffset::default();
+    for frame in 0..20 {
+        inputs.add_input((
+            Velocity::linear(Vec2::X * 100.),
+            crate::player::Jump(true),
+            crate::player::Player::Mask,
+        ));
+        if frame % SYNCFRAME == 0 {
+            offsets.add_offset(frame, Vec3::new(frame as f32, 16., 0.));
+        }
+    }
+    app.insert_resource(inputs)
+        .insert_resource(offsets)
+        .add_event::<GhostVanished>();
+    let ghost = app
+        .world
+        .spawn((
+            Ghost(20),
+            GhostHistory {
+                start: 0,
+                end: None,
+            },
+            end,
+            Transform::from_xyz(200., 16., 0.),
+            Velocity::linear(Vec2::X * 100.),
+            RigidBody::Dynamic,
+            Grounded(true),
+            ActionState::<PlayerInput>::default(),
+        ))
+        .id();
+    (app, ghost)
+}
+
+#[test]
+fn ghost_end_loop() {
+    let (mut app, ghost) = ended_ghost(GhostEnd::Loop);
+    app.add_systems(Update, end_of_trail);
+    app.update();
+    assert_eq!(app.world.get::<Ghost>(ghost).expect("ghost still alive").0, 0);
+    let position = app.world.get::<Transform>(ghost).expect("ghost transform").translation;
+    assert_eq!(position, Vec3::new(0., 16., 0.));
+    assert!(app.world.get::<TrailEnded>(ghost).is_none());
+}
+
+#[test]
+fn ghost_end_despawn() {
+    let (mut app, ghost) = ended_ghost(GhostEnd::Despawn);
+    app.add_systems(Update, end_of_trail);
+    app.update();
+    assert!(app.world.get_entity(ghost).is_none());
+    let events = app.world.resource::<Events<GhostVanished>>();
+    let vanished: Vec<_> = events.get_reader().iter(events).map(|e| e.0).collect();
+    assert_eq!(vanished, vec![Vec3::new(200., 16., 0.)]);
+}
+
+#[test]
+fn ghost_end_freeze() {
+    let (mut app, ghost) = ended_ghost(GhostEnd::Freeze);
+    app.world.entity_mut(ghost).insert(KinematicGhost::new(1.));
+    app.world.get_mut::<KinematicGhost>(ghost).expect("kinematic").frame = 20.;
+    app.add_systems(Update, end_of_trail);
+    app.update();
+    assert!(app.world.get::<TrailEnded>(ghost).is_some());
+    assert!(app.world.get::<KinematicGhost>(ghost).is_none());
+    assert_eq!(app.world.get::<RigidBody>(ghost), Some(&RigidBody::Fixed));
+    assert_eq!(app.world.get::<Velocity>(ghost).expect("velocity").linvel, Vec2::ZERO);
+}
+
+#[test]
+fn ghost_end_chase() {
+    let (mut app, ghost) = ended_ghost(GhostEnd::Chase);
+    app.world.spawn((RealPlayer, Transform::from_xyz(100., 64., 0.)));
+    app.add_systems(Update, (end_of_trail, chase_player).chain());
+    app.update();
+    assert!(app.world.get::<TrailEnded>(ghost).is_some());
+    let state = app
+        .world
+        .get::<ActionState<PlayerInput>>(ghost)
+        .expect("ghost input");
+    assert!(state.pressed(PlayerInput::Left));
+    assert!(!state.pressed(PlayerInput::Right));
+    assert!(state.just_pressed(PlayerInput::Jump));
+}
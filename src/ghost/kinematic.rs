@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{GhostHistory, TrailEnded, GhostRules, GhostRun, PlayerInputs, SyncOffset};
+use crate::player::{Jump, Player};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GhostPlayback {
+    // re-simulate physics from the recorded velocity, snapped back every SYNCFRAME
+    #[default]
+    Physics,
+    // follow the recorded positions, speed is recorded frames per frame
+    Kinematic { speed: f32 },
+}
+
+// drives a ghost from recorded positions instead of physics
+#[derive(Component)]
+pub struct KinematicGhost {
+    pub speed: f32,
+    pub(super) frame: f32,
+}
+
+impl KinematicGhost {
+    pub fn new(speed: f32) -> KinematicGhost {
+        KinematicGhost { speed, frame: 0. }
+    }
+}
+
+pub(super) fn toggle_playback(input: Res<Input<KeyCode>>, mut rules: ResMut<GhostRules>) {
+    if input.just_pressed(KeyCode::F8) {
+        rules.playback = match rules.playback {
+            GhostPlayback::Physics => GhostPlayback::Kinematic { speed: 1. },
+            GhostPlayback::Kinematic { .. } => GhostPlayback::Physics,
+        };
+        info!("Ghost playback: {:?}", rules.playback);
+    }
+}
+
+pub(super) fn update_kinematic_ghost(
+    mut ghosts: Query<
+        (
+            &mut KinematicGhost,
+            &GhostHistory,
+            &mut Transform,
+            &mut Velocity,
+            &mut Jump,
+            &mut Player,
+            Option<&GhostRun>,
+        ),
+        Without<TrailEnded>,
+    >,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+) {
+    for (mut kinematic, history, mut transform, mut velocity, mut jump, mut player, run) in
+        &mut ghosts
+    {
+        kinematic.frame += kinematic.speed;
+        let frame = history.start as f32 + kinematic.frame;
+        if history.end.map_or(false, |end| frame >= end as f32) {
+            continue;
+        }
+        let (trail, offsets) = match run {
+            Some(run) => (&run.trail, &run.offsets),
+            None => (&inputs.trail, &offsets.0),
+        };
+        let Some(position) = offsets.sample(frame) else {continue;};
+        transform.translation = position.extend(transform.translation.z);
+        // only used to pick the animation
+        if let Some((new_v, new_j, new_p)) = trail.get(frame as usize) {
+            *velocity = Velocity::linear(new_v.linvel * kinematic.speed);
+            *jump = new_j;
+            *player = new_p;
+        }
+    }
+}
@@ -0,0 +1,747 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic},
+    ecs::{query::QuerySingleError, system::EntityCommands},
+    prelude::*,
+};
+
+use crate::{
+    animation::{Animation, Animations, SpriteAnimation},
+    interaction::{InteractionSet, PlayerContact, PlayerInteraction},
+    map::{AttemptStarted, LoadedLevel},
+    pause::{is_paused, leaving_play, resumed},
+    player::{Grounded, GroundedCheck, Jump, Player, PlayerStages, RealPlayer},
+    score::PlayerDied,
+    user_input::PlayerInput,
+    Score, GameState,
+};
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+mod end;
+mod kinematic;
+mod rewind;
+mod rules;
+mod share;
+mod style;
+mod time_trial;
+mod trail;
+
+pub use end::{GhostEnd, GhostVanished, TrailEnded};
+pub use kinematic::{GhostPlayback, KinematicGhost};
+pub use rewind::{is_rewinding, CollectHistory, Rewind, Rewound};
+pub use rules::{GhostPreset, GhostRules, GhostTrigger};
+pub use share::GhostRun;
+pub use style::{GhostAppeared, GhostStyle, GhostTint};
+pub use time_trial::{BestGhost, GoalReached, PersonalBest, TimeTrial, TrialFinished};
+use trail::{OffsetTrail, VelocityTrail};
+
+pub const GHOST_RECORDING_BYTES: DiagnosticId =
+    DiagnosticId::from_u128(0x3c1d6a0e_58a4_4d8e_9a52_1f6b7d0c9e21);
+pub const GHOST_PLAYBACK_BYTES: DiagnosticId =
+    DiagnosticId::from_u128(0x8e07b2f4_0c3a_4f61_b3d9_6a25c4e81f07);
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerInputs>()
+            .init_resource::<PlayClock>()
+            .init_resource::<SyncOffset>()
+            .init_resource::<GhostTrailConfig>()
+            .init_resource::<GhostRules>()
+            .init_resource::<GhostPreset>()
+            .init_resource::<Rewind>()
+            .init_resource::<CollectHistory>()
+            .init_resource::<TimeTrial>()
+            .init_resource::<GhostTimer>()
+            .init_resource::<GhostStyle>()
+            .register_type::<GhostStyle>()
+            .add_systems(
+                Update,
+                (
+                    style::assign_tint,
+                    style::apply_tint,
+                    style::draw_trails,
+                    style::telegraph_spawn,
+                    style::spawn_effect,
+                ),
+            )
+            .add_event::<GoalReached>()
+            .add_event::<TrialFinished>()
+            .add_systems(Update, time_trial::load_best.before(handle_ghost_event))
+            .add_systems(
+                OnEnter(GameState::Play),
+                time_trial::spawn_split_text
+                    .run_if(time_trial_enabled)
+                    .run_if(not(resumed)),
+            )
+            .add_systems(
+                OnExit(GameState::Play),
+                time_trial::despawn_split_text.run_if(leaving_play),
+            )
+            .add_systems(
+                OnExit(GameState::Paused),
+                time_trial::despawn_split_text.run_if(leaving_play),
+            )
+            .add_systems(
+                Update,
+                (time_trial::track_splits, time_trial::finish_trial)
+                    .chain()
+                    .run_if(in_state(GameState::Play))
+                    .run_if(time_trial_enabled)
+                    .run_if(not(is_rewinding)),
+            )
+            .add_event::<Rewound>()
+            .configure_set(
+                Update,
+                PlayerStages::Move
+                    .run_if(not(is_rewinding))
+                    .run_if(not(is_paused)),
+            )
+            .add_systems(
+                Update,
+                (rewind::rewind_player, rewind::finish_rewind)
+                    .chain()
+                    .run_if(not(is_paused))
+                    .before(update_ghost)
+                    .before(kinematic::update_kinematic_ghost),
+            )
+            .add_systems(Update, rewind::restore_collectables.after(rewind::finish_rewind))
+            .add_systems(Update, rules::apply_preset.run_if(resource_changed::<GhostPreset>()))
+            .add_systems(Update, rules::update_ghost_groups.run_if(resource_changed::<GhostRules>()))
+            .register_diagnostic(
+                Diagnostic::new(GHOST_RECORDING_BYTES, "ghost_recording", 20).with_suffix("B"),
+            )
+            .register_diagnostic(
+                Diagnostic::new(GHOST_PLAYBACK_BYTES, "ghost_playback", 20).with_suffix("B"),
+            )
+            .add_systems(Last, trail_diagnostics)
+            .add_systems(
+                First,
+                (update_frame, update_ghost_tick)
+                    .chain()
+                    .run_if(not(is_rewinding))
+                    .run_if(not(is_paused)),
+            )
+            .add_systems(
+                First,
+                (start_attempt, tick_clock.run_if(in_state(GameState::Play))).chain(),
+            )
+            .add_systems(
+                Last,
+                save_player_state.run_if(not(is_rewinding)).run_if(not(is_paused)),
+            )
+            .add_systems(Last, prune_history.after(save_player_state))
+            .add_systems(
+                Last,
+                drift_correct.run_if(not(is_rewinding)).run_if(not(is_paused)),
+            )
+            .add_systems(
+                Update,
+                (update_ghost, kinematic::update_kinematic_ghost)
+                    .before(PlayerStages::Move)
+                    .run_if(not(is_rewinding))
+                    .run_if(not(is_paused)),
+            )
+            .add_systems(Update, (test_ghost, kinematic::toggle_playback, end::cycle_end))
+            .add_event::<GhostVanished>()
+            .add_event::<GhostAppeared>()
+            .add_systems(
+                Update,
+                (end::end_of_trail, end::chase_player)
+                    .chain()
+                    .after(update_ghost)
+                    .after(kinematic::update_kinematic_ghost)
+                    .before(PlayerStages::Move)
+                    .run_if(not(is_rewinding))
+                    .run_if(not(is_paused)),
+            )
+            .add_systems(Update, end::spawn_vanish_effect)
+            .add_systems(Update, (share::export_run, share::import_run).run_if(in_state(GameState::Play)))
+            .add_event::<GhostEvents>()
+            .add_systems(Update, handle_ghost_event)
+            .add_systems(
+                Update,
+                kill_player
+                    .in_set(InteractionSet::Handle)
+                    .run_if(not(is_rewinding)),
+            )
+            .add_systems(
+                Update,
+                auto_ghost
+                    .run_if(in_state(GameState::Play))
+                    .run_if(not(is_rewinding)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct Ghost(usize);
+
+// the attempt tick the ghost's frame was recorded on, so the level can be shown to it as it was then,
+// frame is the one it replays from the player's recording, shared runs have none
+#[derive(Component, Default)]
+pub struct GhostTick {
+    pub tick: Option<usize>,
+    pub frame: Option<usize>,
+}
+
+// the absolute frames of the recorded history this ghost replays
+#[derive(Component, Clone, Copy)]
+pub struct GhostHistory {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl GhostHistory {
+    fn frame(&self, ghost: &Ghost) -> Option<usize> {
+        let frame = self.start + ghost.0;
+        match self.end {
+            Some(end) if frame >= end => None,
+            _ => Some(frame),
+        }
+    }
+}
+
+const SYNCFRAME: usize = 10;
+
+#[derive(Resource)]
+pub struct GhostTrailConfig {
+    // recording stops once the trail and offsets use this many bytes
+    pub max_bytes: usize,
+}
+
+impl Default for GhostTrailConfig {
+    fn default() -> Self {
+        GhostTrailConfig {
+            max_bytes: 1024 * 1024,
+        }
+    }
+}
+
+// every frame the player has played, new ghosts replay from segment_start
+#[derive(Resource, Default)]
+pub struct PlayerInputs {
+    trail: VelocityTrail,
+    segment_start: usize,
+    full: bool,
+    // (frame, attempt tick) wherever the two stop counting together, after rewinds and new attempts
+    ticks: Vec<(usize, usize)>,
+    // (frame, platform, attempt tick it set off on) wherever a triggered platform changed
+    triggers: Vec<(usize, IVec2, Option<usize>)>,
+}
+
+impl PlayerInputs {
+    fn add_input(&mut self, state: (Velocity, Jump, Player)) {
+        self.trail.push(state);
+    }
+    fn get_input(&self, frame: usize) -> Option<(Velocity, Jump, Player)> {
+        self.trail.get(frame)
+    }
+    // the frame the player is on
+    pub fn frame(&self) -> usize {
+        self.trail.end()
+    }
+    fn new_segment(&mut self) {
+        self.segment_start = self.trail.end();
+        self.full = false;
+    }
+    fn mark_tick(&mut self, frame: usize, tick: usize) {
+        // anything from here on was rewound away
+        while self.ticks.last().map_or(false, |(start, _)| *start >= frame) {
+            self.ticks.pop();
+        }
+        if self.tick_at(frame) != Some(tick) {
+            self.ticks.push((frame, tick));
+        }
+    }
+    // the attempt tick the player was on when frame was recorded
+    pub fn tick_at(&self, frame: usize) -> Option<usize> {
+        let (start, tick) = self.ticks.iter().rev().find(|(start, _)| *start <= frame)?;
+        Some(tick + frame - start)
+    }
+    // platforms are told apart by where they are placed, they respawn every attempt
+    pub fn mark_trigger(&mut self, frame: usize, platform: IVec2, started: Option<usize>) {
+        // anything from here on was rewound away
+        self.triggers.retain(|(at, on, _)| *on != platform || *at < frame);
+        if self.trigger_at(frame, platform) != started {
+            self.triggers.push((frame, platform, started));
+        }
+    }
+    // the attempt tick the platform had set off on when frame was recorded
+    pub fn trigger_at(&self, frame: usize, platform: IVec2) -> Option<usize> {
+        self.triggers
+            .iter()
+            .rev()
+            .find(|(at, on, _)| *on == platform && *at <= frame)
+            .and_then(|(_, _, started)| *started)
+    }
+}
+
+// physics ticks spent playing, unlike the trail a rewind doesn't take any back
+// and it keeps counting once the recording is full
+#[derive(Resource, Default)]
+pub struct PlayClock {
+    pub ticks: usize,
+    // ticks when the level was last spawned
+    attempt: usize,
+}
+
+impl PlayClock {
+    // ticks since the level was last spawned, for anything in it that moves on its own
+    pub fn attempt_ticks(&self) -> usize {
+        self.ticks - self.attempt
+    }
+}
+
+fn start_attempt(mut attempts: EventReader<AttemptStarted>, mut clock: ResMut<PlayClock>) {
+    if attempts.iter().count() > 0 {
+        clock.attempt = clock.ticks;
+    }
+}
+
+fn tick_clock(mut clock: ResMut<PlayClock>) {
+    clock.ticks += 1;
+}
+
+#[derive(Resource, Default)]
+struct SyncOffset(OffsetTrail);
+
+impl SyncOffset {
+    fn add_offset(&mut self, frame: usize, state: Vec3) {
+        self.0.push(frame, state);
+    }
+    fn get_offset(&self, frame: usize) -> Option<Vec2> {
+        self.0.get(frame)
+    }
+}
+
+fn update_frame(mut query: Query<&mut Ghost>) {
+    for mut frame in query.iter_mut() {
+        frame.0 += 1;
+    }
+}
+
+fn save_player_state(
+    query: Query<(&Velocity, &Jump, &Player, &Transform), With<RealPlayer>>,
+    mut inputs: ResMut<PlayerInputs>,
+    mut offsets: ResMut<SyncOffset>,
+    config: Res<GhostTrailConfig>,
+    clock: Res<PlayClock>,
+) {
+    if inputs.full {
+        return;
+    }
+    if inputs.trail.bytes() + offsets.0.bytes() >= config.max_bytes {
+        warn!("Ghost trail reached {} bytes, recording stopped", config.max_bytes);
+        inputs.full = true;
+        return;
+    }
+    let player = query.single();
+    let frame = inputs.trail.end();
+    inputs.mark_tick(frame, clock.attempt_ticks());
+    inputs.add_input((player.0.clone(), *player.1, *player.2));
+    if frame % SYNCFRAME == 0 {
+        offsets.add_offset(frame, player.3.translation);
+    }
+}
+
+// drop history that no ghost can reach anymore
+fn prune_history(
+    mut inputs: ResMut<PlayerInputs>,
+    mut offsets: ResMut<SyncOffset>,
+    ghosts: Query<&GhostHistory, Without<GhostRun>>,
+) {
+    let keep = ghosts
+        .iter()
+        .map(|history| history.start)
+        .chain(std::iter::once(inputs.segment_start))
+        .min()
+        .unwrap_or_default();
+    inputs.trail.trim_front(keep);
+    offsets.0.trim_front(keep);
+    // the last mark before keep still covers it
+    let first = inputs.ticks.iter().rposition(|(start, _)| *start <= keep);
+    inputs.ticks.drain(..first.unwrap_or_default());
+    // same for each platform's last trigger mark
+    let triggers = std::mem::take(&mut inputs.triggers);
+    inputs.triggers = triggers
+        .iter()
+        .enumerate()
+        .filter(|(i, (at, on, _))| {
+            *at >= keep
+                || !triggers[i + 1..]
+                    .iter()
+                    .any(|(later, other, _)| other == on && *later <= keep)
+        })
+        .map(|(_, mark)| *mark)
+        .collect();
+}
+
+fn update_ghost_tick(
+    mut ghosts: Query<(&Ghost, &GhostHistory, Option<&GhostRun>, &mut GhostTick)>,
+    inputs: Res<PlayerInputs>,
+) {
+    for (ghost, history, run, mut tick) in &mut ghosts {
+        let frame = history.frame(ghost);
+        *tick = match run {
+            // shared runs don't carry the clock, so they count from their first frame
+            Some(_) => GhostTick {
+                tick: frame.map(|_| ghost.0),
+                frame: None,
+            },
+            None => GhostTick {
+                tick: frame.and_then(|frame| inputs.tick_at(frame)),
+                frame,
+            },
+        };
+    }
+}
+
+fn update_ghost(
+    mut ghosts: Query<
+        (
+            &mut Velocity,
+            &mut Jump,
+            &mut Player,
+            &Ghost,
+            &GhostHistory,
+            Option<&GhostRun>,
+        ),
+        (Without<KinematicGhost>, Without<TrailEnded>),
+    >,
+    inputs: Res<PlayerInputs>,
+) {
+    for (mut v, mut j, mut p, ghost, history, run) in &mut ghosts {
+        let Some(frame) = history.frame(ghost) else {continue;};
+        let input = match run {
+            Some(run) => run.trail.get(frame),
+            None => inputs.get_input(frame),
+        };
+        if let Some((new_v, new_j, new_p)) = input {
+            *v = new_v;
+            *j = new_j;
+            *p = new_p;
+        }
+    }
+}
+
+fn trail_diagnostics(
+    mut diagnostics: Diagnostics,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+    ghosts: Query<Option<&GhostRun>, With<Ghost>>,
+) {
+    let recording = inputs.trail.bytes() + offsets.0.bytes();
+    diagnostics.add_measurement(GHOST_RECORDING_BYTES, || recording as f64);
+    let mut playback = 0;
+    let mut shared = false;
+    for run in &ghosts {
+        match run {
+            Some(run) => playback += run.trail.bytes() + run.offsets.bytes(),
+            // ghosts without a run all read the same trail
+            None => shared = true,
+        }
+    }
+    if shared {
+        playback += recording;
+    }
+    diagnostics.add_measurement(GHOST_PLAYBACK_BYTES, || playback as f64);
+}
+
+fn test_ghost(input: Res<Input<KeyCode>>, mut events: EventWriter<GhostEvents>) {
+    if input.just_pressed(KeyCode::F3) {
+        events.send(GhostEvents::SpawnGhost);
+    }
+    if input.just_pressed(KeyCode::F5) {
+        events.send(GhostEvents::ClearGhosts);
+        events.send(GhostEvents::ClearTrail);
+    }
+}
+
+fn drift_correct(
+    mut query: Query<
+        (&Ghost, &GhostHistory, &mut Transform, Option<&GhostRun>),
+        (Without<KinematicGhost>, Without<TrailEnded>),
+    >,
+    offsets: Res<SyncOffset>,
+) {
+    for (ghost, history, mut transform, run) in &mut query {
+        let Some(frame) = history.frame(ghost) else {continue;};
+        if frame % SYNCFRAME != 0 || ghost.0 == 0 {
+            continue;
+        }
+        let offset = match run {
+            Some(run) => run.offsets.get(frame),
+            None => offsets.get_offset(frame),
+        };
+        let Some(offset) = offset else {error!("No Sync for frame {}", frame); continue;};
+        transform.translation = offset.extend(transform.translation.z);
+    }
+}
+
+fn handle_ghost_event(
+    mut events: EventReader<GhostEvents>,
+    mut inputs: ResMut<PlayerInputs>,
+    mut commands: Commands,
+    mut ghosts: Query<
+        (Entity, &mut GhostHistory, Option<&GhostRun>, Option<&BestGhost>),
+        With<Ghost>,
+    >,
+    animations: Res<Animations>,
+    rules: Res<GhostRules>,
+    mut rewind: ResMut<Rewind>,
+    mut collects: ResMut<CollectHistory>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    time_trial: Res<TimeTrial>,
+    offsets: Res<SyncOffset>,
+    mut appeared: EventWriter<GhostAppeared>,
+) {
+    // the personal best ghost does not count towards max_ghosts
+    let mut count = ghosts.iter().filter(|(_, _, _, best)| best.is_none()).count();
+    let mut cleared = false;
+    let mut new_attempt = false;
+    for event in events.iter() {
+        match event {
+            GhostEvents::ClearTrail => {
+                inputs.new_segment();
+                if rewind.cancel() {
+                    rapier_config.physics_pipeline_active = true;
+                }
+                rewind.refill();
+                collects.clear();
+                // ghosts already out keep replaying the segment they started on
+                for (_, mut history, run, _) in &mut ghosts {
+                    if history.end.is_none() && run.is_none() {
+                        history.end = Some(inputs.segment_start);
+                    }
+                }
+                new_attempt = true;
+            }
+            GhostEvents::ClearGhosts => {
+                for (ghost, _, _, _) in &ghosts {
+                    commands.entity(ghost).despawn();
+                }
+                cleared = true;
+            }
+            GhostEvents::ClearGhost(ghost) => {
+                if ghosts.contains(*ghost) {
+                    commands.entity(*ghost).despawn();
+                }
+            }
+            GhostEvents::SpawnGhost => {
+                if rules.max_ghosts.map_or(false, |max| count >= max) {
+                    continue;
+                }
+                count += 1;
+                let Some(handle) = animations.get_animation(Animation::MaskIdle) else {error!("Failed to find animation: Idle"); return;};
+                let mut ghost = commands.spawn((
+                    ghost_bundle(handle),
+                    GhostHistory {
+                        start: inputs.segment_start,
+                        end: None,
+                    },
+                    rules.collision_groups(),
+                    rules.end,
+                ));
+                set_playback(&mut ghost, rules.playback);
+                appeared.send(GhostAppeared(style::spawn_point(&inputs, &offsets)));
+            }
+            GhostEvents::SpawnRun(run) => {
+                let Some(handle) = animations.get_animation(Animation::MaskIdle) else {error!("Failed to find animation: Idle"); return;};
+                let mut ghost = commands.spawn((
+                    ghost_bundle(handle),
+                    GhostHistory {
+                        start: run.start,
+                        end: None,
+                    },
+                    GhostRun::clone(run),
+                    rules.collision_groups(),
+                    rules.end,
+                ));
+                set_playback(&mut ghost, rules.playback);
+            }
+        }
+    }
+    // every new attempt races the personal best from the start
+    if new_attempt && time_trial.enabled {
+        if !cleared {
+            for (ghost, _, _, best) in &ghosts {
+                if best.is_some() {
+                    commands.entity(ghost).despawn();
+                }
+            }
+        }
+        if let Some(best) = time_trial.best() {
+            time_trial::spawn_best_ghost(&mut commands, &animations, &best.run);
+        }
+    }
+}
+
+fn time_trial_enabled(time_trial: Res<TimeTrial>) -> bool {
+    time_trial.enabled
+}
+
+fn set_playback(ghost: &mut EntityCommands, playback: GhostPlayback) {
+    if let GhostPlayback::Kinematic { speed } = playback {
+        ghost.insert((RigidBody::KinematicPositionBased, KinematicGhost::new(speed)));
+    }
+}
+
+fn ghost_bundle(handle: Handle<SpriteAnimation>) -> impl Bundle {
+    (
+        SpriteSheetBundle {
+            texture_atlas: Handle::default(),
+            sprite: TextureAtlasSprite {
+                index: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Player::Mask,
+        handle,
+        Grounded(true),
+        GroundedCheck::default(),
+        ActionState::<PlayerInput>::default(),
+        Jump(false),
+        RigidBody::Dynamic,
+        Velocity::default(),
+        Collider::cuboid(9., 16.),
+        LockedAxes::ROTATION_LOCKED_Z,
+        Friction {
+            coefficient: 5.,
+            combine_rule: CoefficientCombineRule::Multiply,
+        },
+        Damping {
+            linear_damping: 1.,
+            angular_damping: 1.,
+        },
+        (Name::new("Ghost"), PlayerInteraction::Hurt),
+        Ghost(0),
+        GhostTick::default(),
+    )
+}
+
+#[derive(Event)]
+pub enum GhostEvents {
+    ClearTrail,
+    ClearGhosts,
+    SpawnGhost,
+    SpawnRun(Box<GhostRun>),
+    ClearGhost(Entity),
+}
+
+fn kill_player(
+    mut contacts: EventReader<PlayerContact>,
+    mut player: Query<(&mut Transform, &mut Velocity), With<RealPlayer>>,
+    ghosts: Query<(), With<Ghost>>,
+    mut events: EventWriter<GhostEvents>,
+    mut score: ResMut<Score>,
+    mut loaded_level: ResMut<LoadedLevel>,
+    rules: Res<GhostRules>,
+    mut died: EventWriter<PlayerDied>,
+) {
+    for contact in contacts.iter() {
+        if !contact.began(PlayerInteraction::Hurt) {
+            continue;
+        }
+        if ghosts.contains(contact.object) && !rules.lethal {
+            continue;
+        }
+        let Ok((mut pos, mut vel)) = player.get_mut(contact.player) else {continue;};
+        died.send(PlayerDied { score: score.0 });
+        score.0 = 0;
+        events.send(GhostEvents::ClearGhosts);
+        events.send(GhostEvents::ClearTrail);
+        *vel = Velocity::zero();
+        *pos = Transform::IDENTITY;
+        loaded_level.set_changed();
+        // one death is enough however many things were touched
+        break;
+    }
+}
+
+#[derive(Resource)]
+struct GhostTimer(Timer);
+impl Default for GhostTimer {
+    fn default() -> Self {
+        GhostTimer(Timer::from_seconds(2.5, TimerMode::Once))
+    }
+}
+
+fn auto_ghost(
+    has_ghost: Query<&Ghost, Without<BestGhost>>,
+    player: Query<&Transform, With<RealPlayer>>,
+    mut count_down: ResMut<GhostTimer>,
+    mut events: ParamSet<(EventReader<GhostEvents>, EventWriter<GhostEvents>)>,
+    time: Res<Time>,
+    rapier_config: Res<RapierConfiguration>,
+    rules: Res<GhostRules>,
+) {
+    for event in events.p0().iter() {
+        match event {
+            GhostEvents::ClearGhosts => {
+                count_down.0.reset();
+            }
+            _ => {}
+        }
+    }
+    if !rules.trigger.on_timer() {
+        return;
+    }
+    count_down
+        .0
+        .set_duration(std::time::Duration::from_secs_f32(rules.spawn_delay));
+    if let Err(QuerySingleError::NoEntities(_)) = has_ghost.get_single() {
+        let player = player.single();
+        if player.translation.distance(Vec3::ZERO) < 8. && !count_down.0.finished() {
+            events.p1().send(GhostEvents::ClearTrail);
+            count_down.0.reset();
+            return;
+        }
+        // tick with the physics step so replays spawn ghosts on the same frame
+        let delta = match rapier_config.timestep_mode {
+            TimestepMode::Fixed { dt, .. } => std::time::Duration::from_secs_f32(dt),
+            _ => time.delta(),
+        };
+        count_down.0.tick(delta);
+        if count_down.0.finished() {
+            events.p1().send(GhostEvents::SpawnGhost);
+        }
+    }
+}
+
+#[test]
+fn frames_map_to_attempt_ticks() {
+    let mut inputs = PlayerInputs::default();
+    for frame in 0..10 {
+        inputs.mark_tick(frame, frame + 5);
+    }
+    assert_eq!(inputs.ticks, vec![(0, 5)]);
+    // rewound to frame 6, the clock kept going while it scrubbed
+    inputs.mark_tick(6, 20);
+    assert_eq!(inputs.tick_at(3), Some(8));
+    assert_eq!(inputs.tick_at(8), Some(22));
+    inputs.mark_tick(4, 30);
+    assert_eq!(inputs.ticks, vec![(0, 5), (4, 30)]);
+}
+
+#[test]
+fn platform_triggers_follow_the_recording() {
+    let mut inputs = PlayerInputs::default();
+    let platform = IVec2::new(3, 1);
+    for frame in 0..20 {
+        inputs.mark_trigger(frame, platform, (frame >= 10).then_some(10));
+    }
+    assert_eq!(inputs.triggers, vec![(10, platform, Some(10))]);
+    assert_eq!(inputs.trigger_at(5, platform), None);
+    assert_eq!(inputs.trigger_at(15, platform), Some(10));
+    assert_eq!(inputs.trigger_at(15, IVec2::ZERO), None);
+    // a new attempt at frame 20 respawns it waiting
+    inputs.mark_trigger(20, platform, None);
+    assert_eq!(inputs.trigger_at(15, platform), Some(10));
+    assert_eq!(inputs.trigger_at(25, platform), None);
+    // rewound to before it set off, but it stays going
+    inputs.mark_trigger(8, platform, Some(10));
+    assert_eq!(inputs.triggers, vec![(8, platform, Some(10))]);
+}
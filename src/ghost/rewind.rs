@@ -0,0 +1,339 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+use rand::rngs::StdRng;
+
+use super::{
+    set_playback, Ghost, GhostHistory, GhostRules, GhostRun, KinematicGhost, PlayerInputs,
+    SyncOffset, TrailEnded,
+};
+use crate::{
+    animation::Animations,
+    map::{Collectable, MapData},
+    player::{Jump, Player, RealPlayer},
+    user_input::PlayerInput,
+    Score,
+};
+
+#[derive(Resource)]
+pub struct Rewind {
+    // seconds of history each attempt can rewind
+    pub budget: f32,
+    pub remaining: f32,
+    // the frame being scrubbed to while rewind is held
+    target: Option<usize>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Rewind {
+            budget: 3.,
+            remaining: 3.,
+            target: None,
+        }
+    }
+}
+
+impl Rewind {
+    pub fn refill(&mut self) {
+        self.remaining = self.budget;
+    }
+
+    // stops a rewind without keeping it, true if one was running
+    pub fn cancel(&mut self) -> bool {
+        self.target.take().is_some()
+    }
+
+    pub fn target(&self) -> Option<usize> {
+        self.target
+    }
+}
+
+pub fn is_rewinding(rewind: Res<Rewind>) -> bool {
+    rewind.target.is_some()
+}
+
+// sent once the player lets go of rewind, history from frame onwards is gone
+#[derive(Event)]
+pub struct Rewound {
+    pub frame: usize,
+    // frames taken back
+    pub back: usize,
+}
+
+// the collectables as they were just before one was picked up
+struct CollectSnapshot {
+    frame: usize,
+    score: usize,
+    rng: StdRng,
+    collectables: Vec<(Collectable, Vec3)>,
+}
+
+#[derive(Resource, Default)]
+pub struct CollectHistory(Vec<CollectSnapshot>);
+
+impl CollectHistory {
+    pub fn record(
+        &mut self,
+        frame: usize,
+        score: &Score,
+        map_data: &mut MapData,
+        collectables: impl Iterator<Item = (Collectable, Vec3)>,
+    ) {
+        // several pickups on one frame all rewind to the first snapshot
+        if self.0.last().map_or(false, |last| last.frame == frame) {
+            return;
+        }
+        self.0.push(CollectSnapshot {
+            frame,
+            score: score.0,
+            rng: map_data.rng().clone(),
+            collectables: collectables.collect(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+// where a ghost was `back` frames ago, None if it had not spawned yet
+fn rewound_frame(
+    ghost: &Ghost,
+    history: &GhostHistory,
+    kinematic: Option<&KinematicGhost>,
+    back: usize,
+) -> Option<f32> {
+    let local = ghost.0.checked_sub(back)?;
+    Some(match kinematic {
+        Some(kinematic) => {
+            history.start as f32 + (kinematic.frame - back as f32 * kinematic.speed).max(0.)
+        }
+        None => (history.start + local) as f32,
+    })
+}
+
+pub(super) fn rewind_player(
+    mut rewind: ResMut<Rewind>,
+    mut player: Query<
+        (
+            &ActionState<PlayerInput>,
+            &mut Transform,
+            &mut Velocity,
+            &mut Jump,
+            &mut Player,
+        ),
+        With<RealPlayer>,
+    >,
+    mut ghosts: Query<
+        (
+            &Ghost,
+            &GhostHistory,
+            &mut Transform,
+            &mut Visibility,
+            Option<&KinematicGhost>,
+            Option<&GhostRun>,
+        ),
+        Without<RealPlayer>,
+    >,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    time: Res<Time>,
+) {
+    let Ok((state, mut transform, mut velocity, mut jump, mut player)) = player.get_single_mut() else {return;};
+    if !state.pressed(PlayerInput::Rewind) || rewind.remaining <= 0. {
+        return;
+    }
+    let now = inputs.trail.end();
+    let oldest = inputs.segment_start.max(inputs.trail.first());
+    let target = rewind.target.unwrap_or(now);
+    if target <= oldest {
+        return;
+    }
+    let target = target - 1;
+    let Some(position) = offsets.0.sample(target as f32) else {return;};
+    let Some((_, new_j, new_p)) = inputs.get_input(target) else {return;};
+
+    let delta = match rapier_config.timestep_mode {
+        TimestepMode::Fixed { dt, .. } => dt,
+        _ => time.delta_seconds(),
+    };
+    rewind.remaining -= delta;
+    rewind.target = Some(target);
+    // everything holds still while the player scrubs
+    rapier_config.physics_pipeline_active = false;
+    transform.translation = position.extend(transform.translation.z);
+    *velocity = Velocity::zero();
+    *jump = new_j;
+    *player = new_p;
+
+    let back = now - target;
+    for (ghost, history, mut transform, mut visibility, kinematic, run) in &mut ghosts {
+        let Some(frame) = rewound_frame(ghost, history, kinematic, back) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let offsets = match run {
+            Some(run) => &run.offsets,
+            None => &offsets.0,
+        };
+        if let Some(position) = offsets.sample(frame) {
+            transform.translation = position.extend(transform.translation.z);
+        }
+    }
+}
+
+pub(super) fn finish_rewind(
+    mut commands: Commands,
+    mut rewind: ResMut<Rewind>,
+    mut player: Query<(&ActionState<PlayerInput>, &mut Velocity), With<RealPlayer>>,
+    mut ghosts: Query<
+        (
+            Entity,
+            &mut Ghost,
+            &GhostHistory,
+            &mut Velocity,
+            &mut ActionState<PlayerInput>,
+            Option<&mut KinematicGhost>,
+            Option<&TrailEnded>,
+            Option<&GhostRun>,
+        ),
+        Without<RealPlayer>,
+    >,
+    mut inputs: ResMut<PlayerInputs>,
+    mut offsets: ResMut<SyncOffset>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut rewound: EventWriter<Rewound>,
+    rules: Res<GhostRules>,
+) {
+    let Some(target) = rewind.target else {return;};
+    let Ok((state, mut velocity)) = player.get_single_mut() else {return;};
+    if state.pressed(PlayerInput::Rewind) && rewind.remaining > 0. {
+        return;
+    }
+    rewind.target = None;
+    rapier_config.physics_pipeline_active = true;
+
+    let back = inputs.trail.end() - target;
+    if let Some((new_v, _, _)) = inputs.get_input(target) {
+        *velocity = new_v;
+    }
+    inputs.trail.truncate(target);
+    inputs.full = false;
+    offsets.0.truncate(target);
+
+    for (entity, mut ghost, history, mut velocity, mut state, kinematic, ended, run) in
+        &mut ghosts
+    {
+        if ghost.0 < back {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        ghost.0 -= back;
+        if let Some(mut kinematic) = kinematic {
+            kinematic.frame = (kinematic.frame - back as f32 * kinematic.speed).max(0.);
+        }
+        let trail = match run {
+            Some(run) => &run.trail,
+            None => &inputs.trail,
+        };
+        if let Some((new_v, _, _)) = history.frame(&ghost).and_then(|frame| trail.get(frame)) {
+            *velocity = new_v;
+        }
+        // back inside the trail, end_of_trail puts it back if it is still past the end
+        if ended.is_some() {
+            for action in PlayerInput::variants() {
+                state.release(action);
+            }
+            let mut ghost = commands.entity(entity);
+            ghost.remove::<TrailEnded>().insert(RigidBody::Dynamic);
+            set_playback(&mut ghost, rules.playback);
+        }
+    }
+    rewound.send(Rewound { frame: target, back });
+}
+
+pub(super) fn restore_collectables(
+    mut commands: Commands,
+    mut rewound: EventReader<Rewound>,
+    mut history: ResMut<CollectHistory>,
+    collectables: Query<Entity, With<Collectable>>,
+    mut score: ResMut<Score>,
+    mut map_data: ResMut<MapData>,
+    animations: Res<Animations>,
+) {
+    for Rewound { frame, .. } in rewound.iter() {
+        let Some(index) = history.0.iter().position(|snapshot| snapshot.frame >= *frame) else {continue;};
+        let Some(snapshot) = history.0.drain(index..).next() else {continue;};
+        for entity in &collectables {
+            commands.entity(entity).despawn_recursive();
+        }
+        for (collectable, position) in snapshot.collectables {
+            collectable.spawn_at(position, &animations, &mut commands);
+        }
+        score.0 = snapshot.score;
+        *map_data.rng() = snapshot.rng;
+    }
+}
+
+#[test]
+fn rewind_drops_later_history() {
+    let mut app = App::new();
+    let mut inputs = PlayerInputs::default();
+    let mut offsets = SyncOffset::default();
+    for frame in 0..30 {
+        inputs.add_input((Velocity::linear(Vec2::X * frame as f32), Jump(true), Player::Mask));
+        if frame % super::SYNCFRAME == 0 {
+            offsets.add_offset(frame, Vec3::X * frame as f32);
+        }
+    }
+    app.insert_resource(inputs)
+        .insert_resource(offsets)
+        .insert_resource(Rewind {
+            target: Some(12),
+            ..Default::default()
+        })
+        .init_resource::<GhostRules>()
+        .init_resource::<RapierConfiguration>()
+        .add_event::<Rewound>()
+        .add_systems(Update, finish_rewind);
+    app.world.spawn((
+        RealPlayer,
+        ActionState::<PlayerInput>::default(),
+        Velocity::zero(),
+    ));
+    let ghost_bundle = |frame| {
+        (
+            Ghost(frame),
+            GhostHistory {
+                start: 0,
+                end: None,
+            },
+            Velocity::zero(),
+            ActionState::<PlayerInput>::default(),
+        )
+    };
+    let early = app.world.spawn(ghost_bundle(25)).id();
+    let late = app.world.spawn(ghost_bundle(5)).id();
+    app.update();
+
+    assert!(app.world.resource::<Rewind>().target.is_none());
+    assert_eq!(app.world.resource::<PlayerInputs>().trail.end(), 12);
+    assert!(app.world.resource::<SyncOffset>().get_offset(20).is_none());
+    assert_eq!(app.world.get::<Ghost>(early).expect("early ghost kept").0, 7);
+    assert_eq!(
+        app.world.get::<Velocity>(early).expect("early ghost velocity").linvel,
+        Vec2::X * 7.
+    );
+    assert!(app.world.get_entity(late).is_none());
+    let events = app.world.resource::<Events<Rewound>>();
+    let frames: Vec<_> = events.get_reader().iter(events).map(|e| e.frame).collect();
+    assert_eq!(frames, vec![12]);
+    let player = app
+        .world
+        .query_filtered::<&Velocity, With<RealPlayer>>()
+        .single(&app.world)
+        .linvel;
+    assert_eq!(player, Vec2::X * 12.);
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{BestGhost, Ghost, GhostEnd, GhostPlayback};
+use crate::player::PLAYER_GROUP;
+
+pub const GHOST_GROUP: Group = Group::GROUP_2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GhostTrigger {
+    // the first ghost spawns spawn_delay after leaving the start
+    Timer,
+    // a ghost spawns every time a collectable is picked up
+    Collect,
+    Both,
+}
+
+impl GhostTrigger {
+    pub fn on_timer(self) -> bool {
+        matches!(self, GhostTrigger::Timer | GhostTrigger::Both)
+    }
+    pub fn on_collect(self) -> bool {
+        matches!(self, GhostTrigger::Collect | GhostTrigger::Both)
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct GhostRules {
+    pub spawn_delay: f32,
+    pub max_ghosts: Option<usize>,
+    pub playback: GhostPlayback,
+    pub lethal: bool,
+    pub trigger: GhostTrigger,
+    pub end: GhostEnd,
+}
+
+impl Default for GhostRules {
+    fn default() -> Self {
+        GhostPreset::Normal.rules()
+    }
+}
+
+impl GhostRules {
+    pub fn collision_groups(&self) -> CollisionGroups {
+        if self.lethal {
+            CollisionGroups::new(GHOST_GROUP, Group::GROUP_1 | PLAYER_GROUP)
+        } else {
+            CollisionGroups::new(GHOST_GROUP, Group::GROUP_1)
+        }
+    }
+}
+
+// the preset picked in the menu, levels can override it with their own
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GhostPreset {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl GhostPreset {
+    pub fn rules(self) -> GhostRules {
+        match self {
+            GhostPreset::Easy => GhostRules {
+                spawn_delay: 5.,
+                max_ghosts: Some(3),
+                playback: GhostPlayback::Kinematic { speed: 0.75 },
+                lethal: true,
+                trigger: GhostTrigger::Collect,
+                end: GhostEnd::Despawn,
+            },
+            GhostPreset::Normal => GhostRules {
+                spawn_delay: 2.5,
+                max_ghosts: None,
+                playback: GhostPlayback::Physics,
+                lethal: true,
+                trigger: GhostTrigger::Both,
+                end: GhostEnd::Freeze,
+            },
+            GhostPreset::Hard => GhostRules {
+                spawn_delay: 1.5,
+                max_ghosts: None,
+                playback: GhostPlayback::Kinematic { speed: 1. },
+                lethal: true,
+                trigger: GhostTrigger::Both,
+                end: GhostEnd::Chase,
+            },
+        }
+    }
+}
+
+pub(super) fn apply_preset(preset: Res<GhostPreset>, mut rules: ResMut<GhostRules>) {
+    *rules = preset.rules();
+}
+
+pub(super) fn update_ghost_groups(
+    rules: Res<GhostRules>,
+    mut ghosts: Query<&mut CollisionGroups, (With<Ghost>, Without<BestGhost>)>,
+) {
+    for mut groups in &mut ghosts {
+        *groups = rules.collision_groups();
+    }
+}
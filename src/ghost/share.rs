@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    trail::{OffsetTrail, TrailSample, VelocityTrail},
+    GhostEvents, PlayerInputs, SyncOffset,
+};
+use crate::map::{Level, LoadedLevel};
+
+const GHOST_VERSION: u8 = 1;
+const GHOST_DIR: &str = "ghosts";
+
+// a recorded attempt that can be saved and replayed by its own ghost
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct GhostRun {
+    pub level_hash: u64,
+    pub start: usize,
+    pub trail: VelocityTrail,
+    pub offsets: OffsetTrail,
+}
+
+// version 0, from before ghosts kept absolute frames, so it always starts at 0
+#[derive(Serialize, Deserialize)]
+struct GhostRunV0 {
+    level_hash: u64,
+    trail: Vec<(TrailSample, u16)>,
+    offsets: Vec<IVec2>,
+}
+
+impl From<GhostRunV0> for GhostRun {
+    fn from(run: GhostRunV0) -> Self {
+        GhostRun {
+            level_hash: run.level_hash,
+            start: 0,
+            trail: (0, run.trail).into(),
+            offsets: run.offsets.into(),
+        }
+    }
+}
+
+impl GhostRun {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::Error> {
+        let mut bytes = vec![GHOST_VERSION];
+        bincode::options()
+            .with_varint_encoding()
+            .serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<GhostRun, anyhow::Error> {
+        let Some(version) = bytes.first() else {return Err(anyhow::anyhow!("Need atleast one byte in ghost"))};
+        match version {
+            0 => Ok(bincode::options()
+                .with_varint_encoding()
+                .deserialize::<GhostRunV0>(&bytes[1..])?
+                .into()),
+            1 => Ok(bincode::options()
+                .with_varint_encoding()
+                .deserialize(&bytes[1..])?),
+            _ => Err(anyhow::anyhow!("Unsuported ghost version: {}", version)),
+        }
+    }
+
+    pub fn to_base64(&self) -> Result<String, bincode::Error> {
+        Ok(base64::encode(self.to_bytes()?))
+    }
+
+    pub fn from_base64(str: &str) -> Result<GhostRun, anyhow::Error> {
+        GhostRun::from_bytes(&base64::decode(str.trim())?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<GhostRun, anyhow::Error> {
+        GhostRun::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+pub(super) fn current_level_hash(loaded_level: &LoadedLevel, levels: &Assets<Level>) -> Option<u64> {
+    let Some(level) = levels.get(&loaded_level.0) else {error!("No level loaded"); return None;};
+    match level.content_hash() {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            error!("Failed to hash level: {}", e);
+            None
+        }
+    }
+}
+
+pub(super) fn export_run(
+    input: Res<Input<KeyCode>>,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+    loaded_level: Res<LoadedLevel>,
+    levels: Res<Assets<Level>>,
+) {
+    if !input.just_pressed(KeyCode::F6) {
+        return;
+    }
+    let Some(level_hash) = current_level_hash(&loaded_level, &levels) else {return;};
+    let run = GhostRun {
+        level_hash,
+        start: inputs.segment_start,
+        trail: inputs.trail.slice(inputs.segment_start),
+        offsets: offsets.0.slice(inputs.segment_start),
+    };
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = Path::new(GHOST_DIR).join(format!("{:x}-{}.ghost", level_hash, time));
+    match run.save(&path) {
+        Ok(()) => info!("Saved ghost to {:?}", path),
+        Err(e) => error!("Failed to save ghost: {}", e),
+    }
+    match run.to_base64() {
+        Ok(code) => {
+            if let Err(e) = cli_clipboard::set_contents(code) {
+                error!("Failed to copy ghost code: {}", e);
+            } else {
+                info!("Copied ghost code to clipboard");
+            }
+        }
+        Err(e) => error!("Failed to encode ghost: {}", e),
+    }
+}
+
+pub(super) fn import_run(
+    input: Res<Input<KeyCode>>,
+    mut dropped: EventReader<FileDragAndDrop>,
+    mut events: EventWriter<GhostEvents>,
+    loaded_level: Res<LoadedLevel>,
+    levels: Res<Assets<Level>>,
+) {
+    let mut runs = Vec::new();
+    if input.just_pressed(KeyCode::F7) {
+        match cli_clipboard::get_contents() {
+            Ok(code) => runs.push(GhostRun::from_base64(&code)),
+            Err(e) => error!("Failed to read clipboard: {}", e),
+        }
+    }
+    for event in dropped.iter() {
+        if let FileDragAndDrop::DroppedFile { path_buf, .. } = event {
+            if path_buf.extension().map_or(false, |ext| ext == "ghost") {
+                runs.push(GhostRun::load(path_buf));
+            }
+        }
+    }
+    if runs.is_empty() {
+        return;
+    }
+    let Some(level_hash) = current_level_hash(&loaded_level, &levels) else {return;};
+    for run in runs {
+        match run {
+            Ok(run) if run.level_hash != level_hash => {
+                error!("Ghost is for level {:x} not {:x}", run.level_hash, level_hash);
+            }
+            Ok(run) => events.send(GhostEvents::SpawnRun(Box::new(run))),
+            Err(e) => error!("Failed to load ghost: {}", e),
+        }
+    }
+}
+
+#[test]
+fn old_ghost_codes_still_load() {
+    use crate::player::{Jump, Player};
+    use bevy_rapier2d::prelude::Velocity;
+
+    let mut trail = VelocityTrail::default();
+    for _ in 0..3 {
+        trail.push((Velocity::linear(Vec2::X * 20.), Jump(false), Player::Mask));
+    }
+    let (_, runs): (usize, Vec<(TrailSample, u16)>) = trail.into();
+    let old = GhostRunV0 {
+        level_hash: 7,
+        trail: runs,
+        offsets: vec![IVec2::new(32, 16)],
+    };
+    let mut bytes = vec![0];
+    bincode::options()
+        .with_varint_encoding()
+        .serialize_into(&mut bytes, &old)
+        .expect("encode old ghost");
+
+    let run = GhostRun::from_bytes(&bytes).expect("decode old ghost");
+    assert_eq!(run.level_hash, 7);
+    assert_eq!(run.start, 0);
+    assert_eq!(run.trail.len(), 3);
+    assert_eq!(run.offsets.get(0), Some(Vec2::new(2., 1.)));
+    // and the current version round trips
+    let again = GhostRun::from_bytes(&run.to_bytes().expect("encode")).expect("decode");
+    assert_eq!(again.trail.len(), 3);
+    assert!(GhostRun::from_bytes(&[9]).is_err());
+}
@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+
+use super::{
+    BestGhost, Ghost, GhostHistory, GhostRules, GhostRun, GhostTimer,
+    KinematicGhost, PlayerInputs, SyncOffset, TrailEnded, SYNCFRAME,
+};
+use crate::animation::{Animation, Animations, OneShot};
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct GhostStyle {
+    pub alpha: f32,
+    // ghosts take the next tint in order as they spawn
+    pub tints: Vec<Color>,
+    pub best_tint: Color,
+    pub trail: bool,
+    // how many frames ahead the trail line reaches
+    pub trail_frames: usize,
+    pub trail_alpha: f32,
+    pub telegraph: bool,
+    // seconds of warning before the timer spawns a ghost
+    pub telegraph_time: f32,
+}
+
+impl Default for GhostStyle {
+    fn default() -> Self {
+        GhostStyle {
+            alpha: 0.5,
+            tints: vec![
+                Color::rgb(0.6, 0.8, 1.),
+                Color::rgb(1., 0.6, 0.6),
+                Color::rgb(0.6, 1., 0.6),
+                Color::rgb(1., 0.8, 0.4),
+                Color::rgb(0.8, 0.6, 1.),
+            ],
+            best_tint: Color::GOLD,
+            trail: true,
+            trail_frames: 90,
+            trail_alpha: 0.6,
+            telegraph: true,
+            telegraph_time: 1.,
+        }
+    }
+}
+
+impl GhostStyle {
+    fn tint(&self, index: usize) -> Color {
+        match self.tints.len() {
+            0 => Color::WHITE,
+            len => self.tints[index % len],
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GhostTint(pub Color);
+
+pub(super) fn assign_tint(
+    mut commands: Commands,
+    ghosts: Query<(Entity, Option<&BestGhost>), Added<Ghost>>,
+    style: Res<GhostStyle>,
+    mut next: Local<usize>,
+) {
+    for (ghost, best) in &ghosts {
+        let tint = if best.is_some() {
+            style.best_tint
+        } else {
+            *next += 1;
+            style.tint(*next - 1)
+        };
+        commands.entity(ghost).insert(GhostTint(tint));
+    }
+}
+
+pub(super) fn apply_tint(
+    mut ghosts: Query<(&GhostTint, &mut TextureAtlasSprite)>,
+    style: Res<GhostStyle>,
+) {
+    for (tint, mut sprite) in &mut ghosts {
+        sprite.color = tint.0.with_a(style.alpha);
+    }
+}
+
+pub(super) fn draw_trails(
+    mut gizmos: Gizmos,
+    ghosts: Query<
+        (
+            &Ghost,
+            &GhostHistory,
+            &GhostTint,
+            Option<&KinematicGhost>,
+            Option<&GhostRun>,
+        ),
+        Without<TrailEnded>,
+    >,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+    style: Res<GhostStyle>,
+) {
+    if !style.trail {
+        return;
+    }
+    let step = SYNCFRAME / 2;
+    for (ghost, history, tint, kinematic, run) in &ghosts {
+        let frame = match kinematic {
+            Some(kinematic) => history.start as f32 + kinematic.frame,
+            None => (history.start + ghost.0) as f32,
+        };
+        let (end, offsets) = match run {
+            Some(run) => (run.trail.end(), &run.offsets),
+            None => (inputs.trail.end(), &offsets.0),
+        };
+        let end = history.end.map_or(end, |e| e.min(end)) as f32;
+        let points = (0..=style.trail_frames / step)
+            .map(|i| (i, frame + (i * step) as f32))
+            .take_while(|(_, f)| *f < end)
+            .filter_map(|(i, f)| {
+                let fade = 1. - (i * step) as f32 / style.trail_frames.max(1) as f32;
+                let position = offsets.sample(f)?;
+                Some((position, tint.0.with_a(style.trail_alpha * fade)))
+            });
+        gizmos.linestrip_gradient_2d(points);
+    }
+}
+
+// sent by handle_ghost_event when a ghost actually spawns
+#[derive(Event)]
+pub struct GhostAppeared(pub Vec2);
+
+// where the next ghost from the shared trail will appear
+pub(super) fn spawn_point(inputs: &PlayerInputs, offsets: &SyncOffset) -> Vec2 {
+    let first = (inputs.segment_start + SYNCFRAME - 1) / SYNCFRAME * SYNCFRAME;
+    offsets.get_offset(first).unwrap_or_default()
+}
+
+pub(super) fn telegraph_spawn(
+    mut gizmos: Gizmos,
+    timer: Res<GhostTimer>,
+    ghosts: Query<(), (With<Ghost>, Without<BestGhost>)>,
+    inputs: Res<PlayerInputs>,
+    offsets: Res<SyncOffset>,
+    rules: Res<GhostRules>,
+    style: Res<GhostStyle>,
+) {
+    if !style.telegraph || !rules.trigger.on_timer() || !ghosts.is_empty() {
+        return;
+    }
+    let remaining = timer.0.remaining_secs();
+    if timer.0.finished() || timer.0.elapsed_secs() == 0. || remaining > style.telegraph_time {
+        return;
+    }
+    // closes in on the spawn point as the timer runs out
+    let t = 1. - remaining / style.telegraph_time;
+    let color = style.tint(0).with_a(style.alpha * t);
+    gizmos.circle_2d(spawn_point(&inputs, &offsets), 32. * (1. - t) + 8., color);
+}
+
+pub(super) fn spawn_effect(
+    mut commands: Commands,
+    mut appeared: EventReader<GhostAppeared>,
+    animations: Res<Animations>,
+    style: Res<GhostStyle>,
+) {
+    for GhostAppeared(position) in appeared.iter() {
+        if !style.telegraph {
+            continue;
+        }
+        let Some(handle) = animations.get_animation(Animation::Appearing) else {error!("Failed to find animation: Appearing"); continue;};
+        commands.spawn((
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    color: Color::WHITE.with_a(style.alpha),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(1.)),
+                ..Default::default()
+            },
+            handle,
+            OneShot,
+            Name::new("Ghost Appear"),
+        ));
+    }
+}
+
+#[test]
+fn ghost_tints_cycle() {
+    let style = GhostStyle::default();
+    let len = style.tints.len();
+    assert_eq!(style.tint(1), style.tints[1]);
+    assert_eq!(style.tint(len + 1), style.tints[1]);
+    let empty = GhostStyle {
+        tints: Vec::new(),
+        ..Default::default()
+    };
+    assert_eq!(empty.tint(3), Color::WHITE);
+}
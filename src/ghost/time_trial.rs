@@ -0,0 +1,292 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    share::current_level_hash, GhostEnd, GhostHistory, GhostPlayback, GhostRun, PlayClock,
+    PlayerInputs, SyncOffset, GHOST_GROUP,
+};
+use crate::{
+    animation::{Animation, Animations},
+    map::{Level, LoadedLevel},
+    settings::GameSettings,
+    speedrun::tick_seconds,
+    Score,
+};
+
+const BEST_VERSION: u8 = 0;
+const BEST_DIR: &str = "ghosts/best";
+
+// the fastest finish of a level, splits are frames into the run
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PersonalBest {
+    pub frames: usize,
+    pub splits: Vec<usize>,
+    pub run: GhostRun,
+}
+
+impl PersonalBest {
+    fn path(level_hash: u64) -> PathBuf {
+        Path::new(BEST_DIR).join(format!("{:x}.pb", level_hash))
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let path = PersonalBest::path(self.run.level_hash);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut bytes = vec![BEST_VERSION];
+        bincode::options()
+            .with_varint_encoding()
+            .serialize_into(&mut bytes, self)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    pub fn load(level_hash: u64) -> Result<Option<PersonalBest>, anyhow::Error> {
+        let path = PersonalBest::path(level_hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(path)?;
+        let Some(version) = bytes.first() else {return Err(anyhow::anyhow!("Need atleast one byte in personal best"))};
+        match version {
+            0 => Ok(Some(
+                bincode::options()
+                    .with_varint_encoding()
+                    .deserialize(&bytes[1..])?,
+            )),
+            _ => Err(anyhow::anyhow!("Unsuported personal best version: {}", version)),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct TimeTrial {
+    pub enabled: bool,
+    // collectables needed to finish the level
+    pub goal: usize,
+    best: Option<PersonalBest>,
+    attempt_start: Option<usize>,
+    // the clock when the attempt started, so rewinds can't take time off
+    start_tick: usize,
+    start_score: usize,
+    splits: Vec<usize>,
+}
+
+impl Default for TimeTrial {
+    fn default() -> Self {
+        TimeTrial {
+            enabled: false,
+            goal: 10,
+            best: None,
+            attempt_start: None,
+            start_tick: 0,
+            start_score: 0,
+            splits: Vec::new(),
+        }
+    }
+}
+
+impl TimeTrial {
+    pub fn best(&self) -> Option<&PersonalBest> {
+        self.best.as_ref()
+    }
+
+    // ticks into the attempt
+    fn elapsed(&self, clock: &PlayClock) -> usize {
+        clock.ticks.saturating_sub(self.start_tick)
+    }
+}
+
+// sent when the player finishes the level, by touching an End or reaching the trial goal
+#[derive(Event)]
+pub struct GoalReached;
+
+// a finished time trial, frames is how long the run took
+#[derive(Event)]
+pub struct TrialFinished {
+    pub frames: usize,
+    pub run: Box<GhostRun>,
+}
+
+// the personal best ghost, only there to race against
+#[derive(Component)]
+pub struct BestGhost;
+
+#[derive(Component)]
+pub(super) struct SplitText;
+
+pub(super) fn spawn_best_ghost(commands: &mut Commands, animations: &Animations, run: &GhostRun) {
+    let Some(handle) = animations.get_animation(Animation::MaskIdle) else {error!("Failed to find animation: Idle"); return;};
+    let mut ghost = commands.spawn((
+        super::ghost_bundle(handle),
+        GhostHistory {
+            start: run.start,
+            end: None,
+        },
+        run.clone(),
+        // touches nothing, it is only there to race
+        CollisionGroups::new(GHOST_GROUP, Group::NONE),
+        GhostEnd::Despawn,
+        BestGhost,
+    ));
+    // follows the recorded positions exactly
+    super::set_playback(&mut ghost, GhostPlayback::Kinematic { speed: 1. });
+}
+
+pub(super) fn load_best(
+    loaded_level: Res<LoadedLevel>,
+    levels: Res<Assets<Level>>,
+    mut time_trial: ResMut<TimeTrial>,
+) {
+    if !loaded_level.is_changed() {
+        return;
+    }
+    if levels.get(&loaded_level.0).is_none() {
+        return;
+    }
+    let Some(level_hash) = current_level_hash(&loaded_level, &levels) else {return;};
+    time_trial.best = match PersonalBest::load(level_hash) {
+        Ok(best) => best,
+        Err(e) => {
+            error!("Failed to load personal best: {}", e);
+            None
+        }
+    };
+}
+
+pub(super) fn spawn_split_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("Raleway-Regular.ttf"),
+                font_size: 32.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..Default::default()
+        }),
+        SplitText,
+        Name::new("Split Text"),
+    ));
+}
+
+pub(super) fn despawn_split_text(mut commands: Commands, text: Query<Entity, With<SplitText>>) {
+    for entity in &text {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn format_split(frames: usize, best: Option<usize>, dt: f32) -> String {
+    let time = frames as f32 * dt;
+    match best {
+        Some(best) => {
+            let delta = (frames as f32 - best as f32) * dt;
+            format!("{:.2} ({:+.2})", time, delta)
+        }
+        None => format!("{:.2}", time),
+    }
+}
+
+pub(super) fn track_splits(
+    mut time_trial: ResMut<TimeTrial>,
+    inputs: Res<PlayerInputs>,
+    clock: Res<PlayClock>,
+    score: Res<Score>,
+    mut goal: EventWriter<GoalReached>,
+    mut text: Query<&mut Text, With<SplitText>>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    if time_trial.attempt_start != Some(inputs.segment_start) {
+        time_trial.attempt_start = Some(inputs.segment_start);
+        time_trial.start_tick = clock.ticks;
+        time_trial.start_score = score.0;
+        time_trial.splits.clear();
+    }
+    let collected = score.0.saturating_sub(time_trial.start_score);
+    // a rewind can hand collectables back
+    time_trial.splits.truncate(collected);
+    let frame = time_trial.elapsed(&clock);
+    let finished = time_trial.splits.len() >= time_trial.goal;
+    while time_trial.splits.len() < collected {
+        time_trial.splits.push(frame);
+    }
+    if !finished && time_trial.splits.len() >= time_trial.goal {
+        goal.send(GoalReached);
+    }
+
+    let dt = tick_seconds(&rapier_config);
+    let best = time_trial.best();
+    let mut value = format_split(frame, None, dt);
+    if let Some(best) = best {
+        value.push_str(&format!("\nBest {}", format_split(best.frames, None, dt)));
+    }
+    for (i, split) in time_trial.splits.iter().enumerate() {
+        let best = best.and_then(|best| best.splits.get(i).copied());
+        value.push_str(&format!("\n{}: {}", i + 1, format_split(*split, best, dt)));
+    }
+    for mut text in &mut text {
+        text.sections[0].value = value.clone();
+    }
+}
+
+pub(super) fn finish_trial(
+    mut goal: EventReader<GoalReached>,
+    mut time_trial: ResMut<TimeTrial>,
+    inputs: Res<PlayerInputs>,
+    clock: Res<PlayClock>,
+    offsets: Res<SyncOffset>,
+    mut score: ResMut<Score>,
+    mut loaded_level: ResMut<LoadedLevel>,
+    levels: Res<Assets<Level>>,
+    mut finished: EventWriter<TrialFinished>,
+    settings: Res<GameSettings>,
+) {
+    if goal.iter().count() == 0 {
+        return;
+    }
+    let Some(level_hash) = current_level_hash(&loaded_level, &levels) else {return;};
+    let frames = time_trial.elapsed(&clock);
+    let run = GhostRun {
+        level_hash,
+        start: inputs.segment_start,
+        trail: inputs.trail.slice(inputs.segment_start),
+        offsets: offsets.0.slice(inputs.segment_start),
+    };
+    let assisted = settings.gravity_assisted();
+    if !assisted && time_trial.best().map_or(true, |best| frames < best.frames) {
+        let best = PersonalBest {
+            frames,
+            splits: time_trial.splits.clone(),
+            run: run.clone(),
+        };
+        match best.save() {
+            Ok(()) => info!("New personal best: {} frames", frames),
+            Err(e) => error!("Failed to save personal best: {}", e),
+        }
+        time_trial.best = Some(best);
+    }
+    finished.send(TrialFinished {
+        frames,
+        run: Box::new(run),
+    });
+    // straight into the next attempt
+    score.0 = 0;
+    loaded_level.set_changed();
+}
+
+#[test]
+fn split_deltas() {
+    assert_eq!(format_split(120, None, 1. / 60.), "2.00");
+    assert_eq!(format_split(90, Some(120), 1. / 60.), "1.50 (-0.50)");
+    assert_eq!(format_split(150, Some(120), 1. / 60.), "2.50 (+0.50)");
+}
@@ -0,0 +1,316 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use serde::{Deserialize, Serialize};
+
+use super::SYNCFRAME;
+use crate::player::{Jump, Player};
+
+// velocity is stored in 1/8 px/s steps, offsets in 1/16 px
+const VELOCITY_SCALE: f32 = 8.;
+const OFFSET_SCALE: f32 = 16.;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrailSample {
+    x: i16,
+    y: i16,
+    jump: bool,
+    player: Player,
+}
+
+impl TrailSample {
+    fn new((velocity, jump, player): (Velocity, Jump, Player)) -> TrailSample {
+        let quantize = |v: f32| {
+            (v * VELOCITY_SCALE)
+                .round()
+                .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        };
+        TrailSample {
+            x: quantize(velocity.linvel.x),
+            y: quantize(velocity.linvel.y),
+            jump: jump.0,
+            player,
+        }
+    }
+
+    fn state(&self) -> (Velocity, Jump, Player) {
+        (
+            Velocity::linear(Vec2::new(
+                self.x as f32 / VELOCITY_SCALE,
+                self.y as f32 / VELOCITY_SCALE,
+            )),
+            Jump(self.jump),
+            self.player,
+        )
+    }
+}
+
+// run length encoded (Velocity, Jump, Player) for every frame,
+// frames are absolute so the front can be trimmed without moving the rest
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(from = "(usize, Vec<(TrailSample, u16)>)", into = "(usize, Vec<(TrailSample, u16)>)")]
+pub struct VelocityTrail {
+    runs: Vec<(TrailSample, u16)>,
+    // first frame of each run so lookups can binary search
+    starts: Vec<usize>,
+    end: usize,
+}
+
+impl VelocityTrail {
+    pub fn push(&mut self, state: (Velocity, Jump, Player)) {
+        let sample = TrailSample::new(state);
+        match self.runs.last_mut() {
+            Some((last, count)) if *last == sample && *count < u16::MAX => *count += 1,
+            _ => {
+                self.runs.push((sample, 1));
+                self.starts.push(self.end);
+            }
+        }
+        self.end += 1;
+    }
+
+    pub fn get(&self, frame: usize) -> Option<(Velocity, Jump, Player)> {
+        if frame >= self.end || frame < self.first() {
+            return None;
+        }
+        let run = self.starts.partition_point(|start| *start <= frame) - 1;
+        Some(self.runs[run].0.state())
+    }
+
+    pub fn first(&self) -> usize {
+        self.starts.first().copied().unwrap_or(self.end)
+    }
+
+    // the frame the next push will be recorded at
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.first()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // drops every run that ends before frame
+    pub fn trim_front(&mut self, frame: usize) {
+        let keep = self
+            .starts
+            .partition_point(|start| *start <= frame)
+            .saturating_sub(1);
+        if keep > 0 {
+            self.runs.drain(..keep);
+            self.starts.drain(..keep);
+        }
+    }
+
+    // drops every frame from frame onwards, the next push records frame
+    pub fn truncate(&mut self, frame: usize) {
+        if frame >= self.end {
+            return;
+        }
+        let frame = frame.max(self.first());
+        let keep = self.starts.partition_point(|start| *start < frame);
+        self.runs.truncate(keep);
+        self.starts.truncate(keep);
+        if let (Some((_, count)), Some(start)) = (self.runs.last_mut(), self.starts.last()) {
+            *count = (frame - start) as u16;
+        }
+        self.end = frame;
+    }
+
+    // a copy of the frames from start onwards
+    pub fn slice(&self, start: usize) -> VelocityTrail {
+        let mut trail = self.clone();
+        trail.trim_front(start);
+        trail
+    }
+
+    pub fn bytes(&self) -> usize {
+        use std::mem::size_of;
+        self.runs.len() * (size_of::<(TrailSample, u16)>() + size_of::<usize>())
+    }
+}
+
+impl From<(usize, Vec<(TrailSample, u16)>)> for VelocityTrail {
+    fn from((first, runs): (usize, Vec<(TrailSample, u16)>)) -> Self {
+        let mut starts = Vec::with_capacity(runs.len());
+        let mut end = first;
+        for (_, count) in runs.iter() {
+            starts.push(end);
+            end += *count as usize;
+        }
+        VelocityTrail { runs, starts, end }
+    }
+}
+
+impl From<VelocityTrail> for (usize, Vec<(TrailSample, u16)>) {
+    fn from(trail: VelocityTrail) -> Self {
+        (trail.first(), trail.runs)
+    }
+}
+
+// player position every SYNCFRAME frames
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct OffsetTrail {
+    // frame / SYNCFRAME of the first offset
+    first: usize,
+    offsets: Vec<IVec2>,
+}
+
+impl OffsetTrail {
+    pub fn push(&mut self, frame: usize, offset: Vec3) {
+        if self.offsets.is_empty() {
+            self.first = frame / SYNCFRAME;
+        }
+        self.offsets
+            .push((offset.truncate() * OFFSET_SCALE).round().as_ivec2());
+    }
+
+    pub fn get(&self, frame: usize) -> Option<Vec2> {
+        if frame % SYNCFRAME != 0 {
+            return None;
+        }
+        let index = (frame / SYNCFRAME).checked_sub(self.first)?;
+        self.offsets
+            .get(index)
+            .map(|offset| offset.as_vec2() / OFFSET_SCALE)
+    }
+
+    // position at a fractional frame, catmull-rom between the sync points
+    pub fn sample(&self, frame: f32) -> Option<Vec2> {
+        let sync = SYNCFRAME as f32;
+        let index = (frame / sync).floor().max(0.) as usize;
+        let u = (frame / sync).fract();
+        let p1 = self.get(index * SYNCFRAME)?;
+        let Some(p2) = self.get((index + 1) * SYNCFRAME) else {return Some(p1)};
+        let p0 = index
+            .checked_sub(1)
+            .and_then(|i| self.get(i * SYNCFRAME))
+            .unwrap_or(p1);
+        let p3 = self.get((index + 2) * SYNCFRAME).unwrap_or(p2);
+        Some(
+            0.5 * (2. * p1
+                + (p2 - p0) * u
+                + (2. * p0 - 5. * p1 + 4. * p2 - p3) * u * u
+                + (3. * p1 - p0 - 3. * p2 + p3) * u * u * u),
+        )
+    }
+
+    // drops every offset before frame
+    pub fn trim_front(&mut self, frame: usize) {
+        let keep = ((frame + SYNCFRAME - 1) / SYNCFRAME)
+            .saturating_sub(self.first)
+            .min(self.offsets.len());
+        self.offsets.drain(..keep);
+        self.first += keep;
+    }
+
+    // drops every offset from frame onwards
+    pub fn truncate(&mut self, frame: usize) {
+        let keep = ((frame + SYNCFRAME - 1) / SYNCFRAME).saturating_sub(self.first);
+        self.offsets.truncate(keep);
+    }
+
+    pub fn slice(&self, start: usize) -> OffsetTrail {
+        let mut offsets = self.clone();
+        offsets.trim_front(start);
+        offsets
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.offsets.len() * std::mem::size_of::<IVec2>()
+    }
+}
+
+// offsets from frame 0, how ghosts were saved before frames were absolute
+impl From<Vec<IVec2>> for OffsetTrail {
+    fn from(offsets: Vec<IVec2>) -> Self {
+        OffsetTrail { first: 0, offsets }
+    }
+}
+
+#[test]
+fn velocity_trail_compresses() {
+    let mut trail = VelocityTrail::default();
+    for _ in 0..100 {
+        trail.push((Velocity::linear(Vec2::new(200., 0.)), Jump(true), Player::Mask));
+    }
+    trail.push((Velocity::linear(Vec2::new(-12.34, 250.)), Jump(false), Player::Guy));
+    assert_eq!(trail.len(), 101);
+    assert_eq!(trail.runs.len(), 2);
+
+    let (velocity, jump, player) = trail.get(50).expect("frame 50 in trail");
+    assert_eq!(velocity.linvel, Vec2::new(200., 0.));
+    assert!(jump.0 && player == Player::Mask);
+    let (velocity, jump, player) = trail.get(100).expect("frame 100 in trail");
+    assert!((velocity.linvel.x + 12.34).abs() <= 0.5 / VELOCITY_SCALE);
+    assert!(!jump.0 && player == Player::Guy);
+    assert!(trail.get(101).is_none());
+}
+
+#[test]
+fn trail_trim_keeps_absolute_frames() {
+    let mut trail = VelocityTrail::default();
+    let mut offsets = OffsetTrail::default();
+    for frame in 0..40 {
+        trail.push((Velocity::linear(Vec2::X * frame as f32), Jump(true), Player::Mask));
+        if frame % SYNCFRAME == 0 {
+            offsets.push(frame, Vec3::X * frame as f32);
+        }
+    }
+    trail.trim_front(25);
+    offsets.trim_front(25);
+    assert!(trail.get(24).is_none());
+    assert_eq!(trail.get(25).expect("frame 25 kept").0.linvel.x, 25.);
+    assert_eq!(trail.get(39).expect("frame 39 kept").0.linvel.x, 39.);
+    assert!(offsets.get(20).is_none());
+    assert_eq!(offsets.get(30), Some(Vec2::X * 30.));
+
+    let copy: VelocityTrail =
+        bincode::deserialize(&bincode::serialize(&trail).expect("serialize trail"))
+            .expect("deserialize trail");
+    assert_eq!(copy.first(), 25);
+    assert_eq!(copy.get(30).expect("frame 30 in copy").0.linvel.x, 30.);
+}
+
+#[test]
+fn offset_trail_interpolates() {
+    let mut offsets = OffsetTrail::default();
+    for i in 0..4 {
+        offsets.push(i * SYNCFRAME, Vec3::new(i as f32 * 16., 32., 0.));
+    }
+    assert_eq!(offsets.sample(SYNCFRAME as f32), Some(Vec2::new(16., 32.)));
+    let mid = offsets.sample(SYNCFRAME as f32 * 1.5).expect("between sync points");
+    assert!((mid - Vec2::new(24., 32.)).length() < 0.01);
+    // past the last sync point holds the last position
+    assert_eq!(offsets.sample(SYNCFRAME as f32 * 3.5), Some(Vec2::new(48., 32.)));
+}
+
+#[test]
+fn trail_truncate_rewinds_end() {
+    let mut trail = VelocityTrail::default();
+    let mut offsets = OffsetTrail::default();
+    for frame in 0..40 {
+        let speed = if frame < 15 { 1. } else { 2. };
+        trail.push((Velocity::linear(Vec2::X * speed), Jump(true), Player::Mask));
+        if frame % SYNCFRAME == 0 {
+            offsets.push(frame, Vec3::X * frame as f32);
+        }
+    }
+    trail.truncate(12);
+    offsets.truncate(12);
+    assert_eq!(trail.end(), 12);
+    assert_eq!(trail.runs.len(), 1);
+    assert_eq!(trail.get(11).expect("frame 11 kept").0.linvel.x, 1.);
+    assert!(trail.get(12).is_none());
+    assert_eq!(offsets.get(10), Some(Vec2::X * 10.));
+    assert!(offsets.get(20).is_none());
+
+    // recording carries on from the rewind point
+    trail.push((Velocity::linear(Vec2::X * 3.), Jump(true), Player::Mask));
+    offsets.push(20, Vec3::X * 99.);
+    assert_eq!(trail.get(12).expect("frame 12 recorded").0.linvel.x, 3.);
+    assert_eq!(offsets.get(20), Some(Vec2::X * 99.));
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::player::RealPlayer;
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerContact>()
+            .add_systems(
+                Update,
+                dispatch_contacts.in_set(InteractionSet::Dispatch),
+            )
+            .configure_set(Update, InteractionSet::Handle.after(InteractionSet::Dispatch))
+            .add_systems(Update, bounce_player.in_set(InteractionSet::Handle));
+    }
+}
+
+#[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone)]
+pub enum InteractionSet {
+    // turns rapier collision events into PlayerContact events
+    Dispatch,
+    // systems reading PlayerContact go in here
+    Handle,
+}
+
+// how a map object or ghost reacts when it touches the player
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub enum PlayerInteraction {
+    Collect,
+    Hurt,
+    // sets the player's upward speed
+    Bounce(f32),
+    // nothing by itself, for systems that care about the player being inside
+    Trigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactPhase {
+    Begin,
+    End,
+}
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerContact {
+    pub player: Entity,
+    pub object: Entity,
+    pub interaction: PlayerInteraction,
+    pub phase: ContactPhase,
+}
+
+impl PlayerContact {
+    pub fn began(&self, interaction: PlayerInteraction) -> bool {
+        self.phase == ContactPhase::Begin && self.interaction == interaction
+    }
+}
+
+fn dispatch_contacts(
+    mut collisions: EventReader<CollisionEvent>,
+    mut contacts: EventWriter<PlayerContact>,
+    players: Query<(), With<RealPlayer>>,
+    objects: Query<&PlayerInteraction>,
+) {
+    for collision in collisions.iter() {
+        let (a, b, phase) = match collision {
+            CollisionEvent::Started(a, b, _) => (*a, *b, ContactPhase::Begin),
+            CollisionEvent::Stopped(a, b, _) => (*a, *b, ContactPhase::End),
+        };
+        let (player, object) = if players.contains(a) {
+            (a, b)
+        } else if players.contains(b) {
+            (b, a)
+        } else {
+            continue;
+        };
+        // objects despawned before the contact ended have nothing left to handle
+        let Ok(interaction) = objects.get(object) else {continue;};
+        contacts.send(PlayerContact {
+            player,
+            object,
+            interaction: *interaction,
+            phase,
+        });
+    }
+}
+
+fn bounce_player(
+    mut contacts: EventReader<PlayerContact>,
+    mut players: Query<&mut Velocity, With<RealPlayer>>,
+) {
+    for contact in contacts.iter() {
+        let PlayerInteraction::Bounce(speed) = contact.interaction else {continue;};
+        if contact.phase != ContactPhase::Begin {
+            continue;
+        }
+        if let Ok(mut velocity) = players.get_mut(contact.player) {
+            velocity.linvel.y = speed;
+        }
+    }
+}
+
+#[test]
+fn contacts_only_reach_player_objects() {
+    let mut app = App::new();
+    app.add_event::<CollisionEvent>()
+        .add_event::<PlayerContact>()
+        .add_systems(Update, dispatch_contacts);
+    let player = app.world.spawn(RealPlayer).id();
+    let coin = app.world.spawn(PlayerInteraction::Collect).id();
+    let wall = app.world.spawn_empty().id();
+    let ghost = app.world.spawn(PlayerInteraction::Hurt).id();
+    let mut collisions = app.world.resource_mut::<Events<CollisionEvent>>();
+    collisions.send(CollisionEvent::Started(coin, player, CollisionEventFlags::SENSOR));
+    collisions.send(CollisionEvent::Started(player, wall, CollisionEventFlags::empty()));
+    collisions.send(CollisionEvent::Started(ghost, coin, CollisionEventFlags::empty()));
+    collisions.send(CollisionEvent::Stopped(coin, player, CollisionEventFlags::SENSOR));
+    app.update();
+
+    let events = app.world.resource::<Events<PlayerContact>>();
+    let contacts: Vec<_> = events.get_reader().iter(events).copied().collect();
+    assert_eq!(
+        contacts,
+        vec![
+            PlayerContact {
+                player,
+                object: coin,
+                interaction: PlayerInteraction::Collect,
+                phase: ContactPhase::Begin,
+            },
+            PlayerContact {
+                player,
+                object: coin,
+                interaction: PlayerInteraction::Collect,
+                phase: ContactPhase::End,
+            },
+        ]
+    );
+    assert!(contacts[0].began(PlayerInteraction::Collect));
+    assert!(!contacts[1].began(PlayerInteraction::Collect));
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ghost::TrialFinished,
+    map::LevelHash,
+    player::{Player, RealPlayer},
+    replay::SaveReplay,
+    score::PlayerDied,
+    settings::GameSettings,
+};
+
+const LEADERBOARD_VERSION: u8 = 0;
+const LEADERBOARD_PATH: &str = "saves/leaderboard.ron";
+const LEADERBOARD_GHOSTS: &str = "ghosts/leaderboard";
+// runs kept per level and mode
+pub const LEADERBOARD_SIZE: usize = 10;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Leaderboard::load_or_default(Path::new(LEADERBOARD_PATH)))
+            .init_resource::<PlayerName>()
+            .init_resource::<LeaderboardView>()
+            .add_systems(Update, (submit_score, submit_time));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Score,
+    TimeTrial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunResult {
+    Score(usize),
+    // frames taken to finish
+    Time(usize),
+}
+
+impl RunResult {
+    pub fn mode(&self) -> GameMode {
+        match self {
+            RunResult::Score(_) => GameMode::Score,
+            RunResult::Time(_) => GameMode::TimeTrial,
+        }
+    }
+
+    // true if self ranks above other
+    fn beats(&self, other: &RunResult) -> bool {
+        match (self, other) {
+            (RunResult::Score(a), RunResult::Score(b)) => a > b,
+            (RunResult::Time(a), RunResult::Time(b)) => a < b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub result: RunResult,
+    pub character: Player,
+    // unix seconds
+    pub date: u64,
+    #[serde(default)]
+    pub ghost: Option<PathBuf>,
+    #[serde(default)]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Resource, Serialize, Deserialize)]
+pub struct Leaderboard {
+    version: u8,
+    // keyed by level content hash, best first within each mode
+    levels: HashMap<u64, Vec<LeaderboardEntry>>,
+}
+
+impl Default for Leaderboard {
+    fn default() -> Self {
+        Leaderboard {
+            version: LEADERBOARD_VERSION,
+            levels: HashMap::new(),
+        }
+    }
+}
+
+impl Leaderboard {
+    pub fn load(path: &Path) -> Result<Leaderboard, anyhow::Error> {
+        let leaderboard: Leaderboard = ron::from_str(&std::fs::read_to_string(path)?)?;
+        match leaderboard.version {
+            0 => Ok(leaderboard),
+            v => Err(anyhow::anyhow!("Unsuported leaderboard version: {}", v)),
+        }
+    }
+
+    pub fn load_or_default(path: &Path) -> Leaderboard {
+        if !path.exists() {
+            return Leaderboard::default();
+        }
+        Leaderboard::load(path).unwrap_or_else(|e| {
+            error!("Failed to load leaderboard: {}", e);
+            Leaderboard::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn entries(
+        &self,
+        level_hash: u64,
+        mode: GameMode,
+    ) -> impl Iterator<Item = &LeaderboardEntry> {
+        self.levels
+            .get(&level_hash)
+            .into_iter()
+            .flatten()
+            .filter(move |entry| entry.result.mode() == mode)
+    }
+
+    // would result make it onto the board
+    pub fn qualifies(&self, level_hash: u64, result: RunResult) -> bool {
+        let entries: Vec<_> = self.entries(level_hash, result.mode()).collect();
+        entries.len() < LEADERBOARD_SIZE || entries.iter().any(|entry| result.beats(&entry.result))
+    }
+
+    // returns the rank the entry got, None if it did not make the top LEADERBOARD_SIZE
+    pub fn insert(&mut self, level_hash: u64, entry: LeaderboardEntry) -> Option<usize> {
+        let mode = entry.result.mode();
+        let level = self.levels.entry(level_hash).or_default();
+        // ties go to the run that got there first
+        let index = level
+            .iter()
+            .position(|other| entry.result.beats(&other.result))
+            .unwrap_or(level.len());
+        level.insert(index, entry);
+        let mut rank = None;
+        let mut kept = 0;
+        let mut i = 0;
+        level.retain(|other| {
+            let keep = if other.result.mode() != mode {
+                true
+            } else {
+                kept += 1;
+                if i == index {
+                    rank = (kept <= LEADERBOARD_SIZE).then_some(kept);
+                }
+                kept <= LEADERBOARD_SIZE
+            };
+            i += 1;
+            keep
+        });
+        rank
+    }
+}
+
+#[derive(Resource)]
+pub struct PlayerName(pub String);
+
+impl Default for PlayerName {
+    fn default() -> Self {
+        PlayerName(
+            std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "Player".to_string()),
+        )
+    }
+}
+
+// which mode the leaderboard screen shows
+#[derive(Resource, Default)]
+pub struct LeaderboardView(pub GameMode);
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// yyyy-mm-dd from unix seconds
+pub fn format_date(secs: u64) -> String {
+    // days to civil date, from Howard Hinnant's date algorithms
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn submit(leaderboard: &mut Leaderboard, level_hash: u64, entry: LeaderboardEntry) {
+    if let Some(rank) = leaderboard.insert(level_hash, entry) {
+        info!("Leaderboard rank {}", rank);
+        if let Err(e) = leaderboard.save(Path::new(LEADERBOARD_PATH)) {
+            error!("Failed to save leaderboard: {}", e);
+        }
+    }
+}
+
+fn submit_score(
+    mut died: EventReader<PlayerDied>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut save_replay: EventWriter<SaveReplay>,
+    level_hash: Res<LevelHash>,
+    name: Res<PlayerName>,
+    player: Query<&Player, With<RealPlayer>>,
+    settings: Res<GameSettings>,
+) {
+    for PlayerDied { score } in died.iter() {
+        let Some(hash) = level_hash.0 else {continue;};
+        if settings.gravity_assisted() {
+            continue;
+        }
+        let result = RunResult::Score(*score);
+        if *score == 0 || !leaderboard.qualifies(hash, result) {
+            continue;
+        }
+        let replay = SaveReplay::timestamped();
+        let entry = LeaderboardEntry {
+            name: name.0.clone(),
+            result,
+            character: player.get_single().copied().unwrap_or(Player::Mask),
+            date: unix_time(),
+            ghost: None,
+            replay: Some(replay.0.clone()),
+        };
+        save_replay.send(replay);
+        submit(&mut leaderboard, hash, entry);
+    }
+}
+
+fn submit_time(
+    mut finished: EventReader<TrialFinished>,
+    mut leaderboard: ResMut<Leaderboard>,
+    name: Res<PlayerName>,
+    player: Query<&Player, With<RealPlayer>>,
+    settings: Res<GameSettings>,
+) {
+    for TrialFinished { frames, run } in finished.iter() {
+        let result = RunResult::Time(*frames);
+        if settings.gravity_assisted() || !leaderboard.qualifies(run.level_hash, result) {
+            continue;
+        }
+        let date = unix_time();
+        let path =
+            Path::new(LEADERBOARD_GHOSTS).join(format!("{:x}-{}.ghost", run.level_hash, date));
+        let ghost = match run.save(&path) {
+            Ok(()) => Some(path),
+            Err(e) => {
+                error!("Failed to save leaderboard ghost: {}", e);
+                None
+            }
+        };
+        let entry = LeaderboardEntry {
+            name: name.0.clone(),
+            result,
+            character: player.get_single().copied().unwrap_or(Player::Mask),
+            date,
+            ghost,
+            replay: None,
+        };
+        submit(&mut leaderboard, run.level_hash, entry);
+    }
+}
+
+#[cfg(test)]
+fn entry(result: RunResult) -> LeaderboardEntry {
+    LeaderboardEntry {
+        name: "test".to_string(),
+        result,
+        character: Player::Mask,
+        date: 0,
+        ghost: None,
+        replay: None,
+    }
+}
+
+#[test]
+fn leaderboard_keeps_top_runs_per_mode() {
+    let mut leaderboard = Leaderboard::default();
+    for score in 1..=LEADERBOARD_SIZE {
+        assert!(leaderboard.insert(7, entry(RunResult::Score(score))).is_some());
+    }
+    assert_eq!(leaderboard.insert(7, entry(RunResult::Time(600))), Some(1));
+    assert_eq!(leaderboard.insert(7, entry(RunResult::Time(300))), Some(1));
+    assert!(!leaderboard.qualifies(7, RunResult::Score(1)));
+    assert_eq!(leaderboard.insert(7, entry(RunResult::Score(1))), None);
+    assert_eq!(leaderboard.insert(7, entry(RunResult::Score(5))), Some(7));
+
+    let scores: Vec<_> = leaderboard.entries(7, GameMode::Score).map(|e| e.result).collect();
+    assert_eq!(scores.len(), LEADERBOARD_SIZE);
+    assert_eq!(scores[0], RunResult::Score(LEADERBOARD_SIZE));
+    assert_eq!(scores[LEADERBOARD_SIZE - 1], RunResult::Score(2));
+    let times: Vec<_> = leaderboard.entries(7, GameMode::TimeTrial).map(|e| e.result).collect();
+    assert_eq!(times, vec![RunResult::Time(300), RunResult::Time(600)]);
+    assert_eq!(leaderboard.entries(8, GameMode::Score).count(), 0);
+}
+
+#[test]
+fn dates_format_as_ymd() {
+    assert_eq!(format_date(0), "1970-01-01");
+    assert_eq!(format_date(951_782_400), "2000-02-29");
+    assert_eq!(format_date(1_792_368_000), "2026-10-19");
+}
@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::RapierConfiguration;
+
+use crate::{
+    ghost::{GhostEvents, GoalReached, TimeTrial},
+    map::{CurrentPack, LevelHash, LevelPack, LoadedLevel},
+    profile::Profile,
+    score::PlayerDied,
+    settings::GameSettings,
+    speedrun::tick_seconds,
+    GameState, Score,
+};
+
+pub struct LevelCompletePlugin;
+
+impl Plugin for LevelCompletePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStats>()
+            .init_resource::<LevelSummary>()
+            .add_systems(Update, count_deaths)
+            .add_systems(
+                Update,
+                (track_time, complete_level)
+                    .chain()
+                    .after(count_deaths)
+                    .run_if(in_state(GameState::Play)),
+            );
+    }
+}
+
+// running totals for the level being played
+#[derive(Resource, Default)]
+struct LevelStats {
+    level: Option<u64>,
+    // physics ticks spent in play
+    ticks: usize,
+    deaths: usize,
+}
+
+impl LevelStats {
+    // starts over when a different level is loaded
+    fn check_level(&mut self, level: Option<u64>) {
+        if self.level != level {
+            *self = LevelStats {
+                level,
+                ..Default::default()
+            };
+        }
+    }
+}
+
+// what the level complete screen shows
+#[derive(Resource, Default)]
+pub struct LevelSummary {
+    pub time: f32,
+    pub score: usize,
+    pub deaths: usize,
+    pub has_next: bool,
+    // played with lower gravity, so the time isn't kept as a best
+    pub assisted: bool,
+}
+
+fn count_deaths(
+    mut died: EventReader<PlayerDied>,
+    mut stats: ResMut<LevelStats>,
+    level_hash: Res<LevelHash>,
+) {
+    for _ in died.iter() {
+        stats.check_level(level_hash.0);
+        stats.deaths += 1;
+    }
+}
+
+// physics steps once a frame in play, so a slow frame doesn't cost time
+fn track_time(mut stats: ResMut<LevelStats>, level_hash: Res<LevelHash>) {
+    stats.check_level(level_hash.0);
+    stats.ticks += 1;
+}
+
+fn complete_level(
+    mut goal: EventReader<GoalReached>,
+    mut stats: ResMut<LevelStats>,
+    mut summary: ResMut<LevelSummary>,
+    mut events: EventWriter<GhostEvents>,
+    mut next: ResMut<NextState<GameState>>,
+    score: Res<Score>,
+    time_trial: Res<TimeTrial>,
+    current_pack: Res<CurrentPack>,
+    packs: Res<Assets<LevelPack>>,
+    rapier_config: Res<RapierConfiguration>,
+    settings: Res<GameSettings>,
+) {
+    if goal.iter().count() == 0 {
+        return;
+    }
+    // time trials go straight into the next attempt
+    if time_trial.enabled {
+        return;
+    }
+    *summary = LevelSummary {
+        time: stats.ticks as f32 * tick_seconds(&rapier_config),
+        score: score.0,
+        deaths: stats.deaths,
+        has_next: current_pack.next(&packs).is_some(),
+        assisted: settings.gravity_assisted(),
+    };
+    stats.ticks = 0;
+    stats.deaths = 0;
+    events.send(GhostEvents::ClearGhosts);
+    next.set(GameState::LevelComplete);
+}
+
+// loads the level after this one in the pack, back to the menu if there is none
+pub fn load_next_level(world: &mut World) {
+    let packs = world.resource::<Assets<LevelPack>>();
+    let current_pack = world.resource::<CurrentPack>();
+    let profile = world.resource::<Profile>();
+    let next = current_pack
+        .next(packs)
+        .filter(|(_, path)| {
+            packs
+                .get(&current_pack.pack)
+                .map_or(true, |pack| profile.has_unlocked(pack, path))
+        })
+        .map(|(index, path)| (index, path.to_string()));
+    let Some((index, path)) = next else {
+        world.resource_mut::<NextState<GameState>>().set(GameState::Menu);
+        return;
+    };
+    let level = world.resource::<AssetServer>().load(path.as_str());
+    world.resource_mut::<CurrentPack>().index = Some(index);
+    world.resource_mut::<LoadedLevel>().0 = level;
+    world.resource_mut::<Score>().0 = 0;
+    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
+}
+
+#[test]
+fn stats_reset_on_new_level() {
+    let mut stats = LevelStats::default();
+    stats.check_level(Some(1));
+    stats.deaths += 2;
+    stats.ticks += 3;
+    stats.check_level(Some(1));
+    assert_eq!(stats.deaths, 2);
+    stats.check_level(Some(2));
+    assert_eq!(stats.deaths, 0);
+    assert_eq!(stats.ticks, 0);
+    assert_eq!(stats.level, Some(2));
+}
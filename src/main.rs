@@ -1,18 +1,30 @@
 use bevy::{asset::ChangeWatcher, prelude::*};
 // use bevy_inspector_egui_rapier::InspectableRapierPlugin;
 use bevy_rapier2d::prelude::*;
-use ghost::GhostEvents;
-use leafwing_input_manager::prelude::*;
+use ghost::{CollectHistory, GhostEvents, GhostRules, PlayerInputs};
 
+mod achievement;
 mod animation;
+mod audio;
 mod editor;
 mod ghost;
+mod interaction;
+mod leaderboard;
+mod level_complete;
 mod map;
 mod menu;
+mod pause;
 mod player;
+mod profile;
+mod replay;
+mod score;
+mod settings;
+mod speedrun;
+mod transition;
 mod user_input;
 
 use animation::*;
+use interaction::{InteractionSet, PlayerContact, PlayerInteraction};
 use map::*;
 use player::*;
 
@@ -32,11 +44,17 @@ fn main() {
         .add_plugins(PhoxAnimationPlugin)
         .add_systems(Startup, spawn_cam)
         .add_systems(Startup, spawn_map)
-        .add_systems(Update, get_collectable)
+        .add_plugins(interaction::InteractionPlugin)
+        .add_systems(
+            Update,
+            get_collectable
+                .in_set(InteractionSet::Handle)
+                .run_if(not(ghost::is_rewinding)),
+        )
         .register_type::<TextureAtlasSprite>()
-        .add_plugins(InputManagerPlugin::<user_input::PlayerInput>::default())
+        .add_plugins(user_input::UserInputPlugin)
         .insert_resource(RapierConfiguration {
-            gravity: Vec2::Y * -294.,
+            gravity: Vec2::Y * -settings::GRAVITY,
             timestep_mode: TimestepMode::Fixed {
                 dt: 1. / 60.,
                 substeps: 1,
@@ -52,6 +70,17 @@ fn main() {
         .add_state::<GameState>()
         .add_plugins(menu::MenuPlugin)
         .add_plugins(editor::LevelEditorPlugin)
+        .add_plugins(replay::ReplayPlugin)
+        .add_plugins(score::ScorePlugin)
+        .add_plugins(leaderboard::LeaderboardPlugin)
+        .add_plugins(achievement::AchievementPlugin)
+        .add_plugins(pause::PausePlugin)
+        .add_plugins(level_complete::LevelCompletePlugin)
+        .add_plugins(transition::TransitionPlugin)
+        .add_plugins(speedrun::SpeedrunPlugin)
+        .add_plugins(settings::SettingsPlugin)
+        .add_plugins(audio::GameAudioPlugin)
+        .add_plugins(profile::ProfilePlugin)
         .add_plugins(belly::prelude::BellyPlugin)
         .run()
 }
@@ -68,34 +97,44 @@ struct CurrentLevel(Handle<Level>, bool);
 
 fn spawn_map(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.insert_resource(LoadedLevel(asset_server.load("Levels/test.lvl.ron")));
+    commands.insert_resource(CurrentPack {
+        pack: asset_server.load("Levels/main.pack.ron"),
+        index: Some(0),
+    });
 }
 
 fn get_collectable(
     mut commands: Commands,
-    player: Query<Entity, With<RealPlayer>>,
-    mut collectables: Query<&Collectable>,
-    rapier_context: Res<RapierContext>,
+    mut contacts: EventReader<PlayerContact>,
+    collectables: Query<(&Collectable, &Transform)>,
     mut events: EventWriter<GhostEvents>,
     mut map_events: EventWriter<MapEvent>,
     mut score: ResMut<Score>,
+    rules: Res<GhostRules>,
+    inputs: Res<PlayerInputs>,
+    mut collects: ResMut<CollectHistory>,
+    mut map_data: ResMut<MapData>,
 ) {
-    let entity = player.single();
-    /* Iterate through all the intersection pairs involving a specific collider. */
-    for (collider1, collider2, intersecting) in rapier_context.intersections_with(entity) {
-        if intersecting {
-            if let Ok(collectable) = collectables.get_mut(collider2) {
-                events.send(GhostEvents::SpawnGhost);
-                map_events.send(MapEvent::spawn(Clone::clone(collectable)));
-                score.0 += 1;
-                commands.entity(collider2).despawn_recursive();
-            }
-            if let Ok(collectable) = collectables.get_mut(collider1) {
-                map_events.send(MapEvent::spawn(Clone::clone(collectable)));
-                events.send(GhostEvents::SpawnGhost);
-                score.0 += 1;
-                commands.entity(collider2).despawn_recursive();
-            }
+    for contact in contacts.iter() {
+        if !contact.began(PlayerInteraction::Collect) {
+            continue;
+        }
+        let Ok((collectable, _)) = collectables.get(contact.object) else {continue;};
+        // so a rewind can put back what was here before the pickup
+        collects.record(
+            inputs.frame(),
+            &score,
+            &mut map_data,
+            collectables
+                .iter()
+                .map(|(collectable, transform)| (Clone::clone(collectable), transform.translation)),
+        );
+        if rules.trigger.on_collect() {
+            events.send(GhostEvents::SpawnGhost);
         }
+        map_events.send(MapEvent::spawn(Clone::clone(collectable)));
+        score.0 += 1;
+        commands.entity(contact.object).despawn_recursive();
     }
 }
 
@@ -110,4 +149,11 @@ enum GameState {
     InputLevelBase64,
     InputLevelName,
     LevelEditor,
+    Results,
+    Leaderboard,
+    Achievements,
+    Paused,
+    LevelComplete,
+    Profiles,
+    Settings,
 }
@@ -0,0 +1,96 @@
+use super::*;
+use crate::animation::{Animation, Animations, ThenPlay};
+use crate::interaction::{PlayerContact, PlayerInteraction};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// a flag the player raises by running past it
+#[derive(Component, Clone, Copy, Deserialize, Serialize, Reflect, Default)]
+pub struct Checkpoint {
+    pub position: IVec2,
+}
+
+impl MapObject for Checkpoint {
+    fn spawn(
+        &self,
+        terrain: &Animations,
+        commands: &mut Commands,
+        map_data: &mut MapData,
+    ) -> Option<Entity> {
+        let Some(animation) = terrain.get_animation(Animation::CheckpointNoFlag) else {error!("Animation for Checkpoint not loaded"); return None;};
+        self.set_full(map_data);
+        // same 64x64 sprite layout as the End
+        let pos = (self.position * 16).as_vec2() + Vec2::Y * 24.;
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: Transform::from_translation(pos.extend(1.)),
+                        rigid_body: RigidBody::Fixed,
+                        collider: Collider::cuboid(8., 24.),
+                        item: *self,
+                        ..Default::default()
+                    },
+                    Handle::<TextureAtlas>::default(),
+                    TextureAtlasSprite::default(),
+                    animation,
+                    Sensor,
+                    PlayerInteraction::Trigger,
+                    Name::new("Checkpoint"),
+                ))
+                .id(),
+        )
+    }
+    fn object_type(&self) -> super::levels::MapObjectType {
+        super::levels::MapObjectType::Checkpoint
+    }
+    fn serialize(&self) -> bevy::reflect::serde::Serializable {
+        bevy::reflect::serde::Serializable::Borrowed(self)
+    }
+    fn clone(&self) -> Box<dyn MapObject> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+    fn set_full(&self, map: &mut MapData) {
+        map.set_full(self.position);
+    }
+}
+
+impl DrawProps for Checkpoint {
+    fn draw_props(_root: Entity) -> belly::core::eml::Eml {
+        use belly::prelude::*;
+        eml!(<label {_root} value="Checkpoint Not done"/>)
+    }
+    fn ui_draw(_editor: Entity) -> belly::core::eml::Eml {
+        use belly::prelude::*;
+        eml!(<label {_editor} value="Checkpoint Not done"/>)
+    }
+}
+
+#[derive(Component)]
+struct Raised;
+
+// sent the first time the player passes each checkpoint
+#[derive(Event)]
+pub struct CheckpointReached(pub Entity);
+
+pub(super) fn touch_checkpoint(
+    mut commands: Commands,
+    mut contacts: EventReader<PlayerContact>,
+    checkpoints: Query<(), (With<Checkpoint>, Without<Raised>)>,
+    animations: Res<Animations>,
+    mut reached: EventWriter<CheckpointReached>,
+) {
+    for contact in contacts.iter() {
+        if !contact.began(PlayerInteraction::Trigger) || !checkpoints.contains(contact.object) {
+            continue;
+        }
+        reached.send(CheckpointReached(contact.object));
+        let mut checkpoint = commands.entity(contact.object);
+        checkpoint.insert(Raised);
+        let (Some(out), Some(idle)) = (
+            animations.get_animation(Animation::CheckpointFlagOut),
+            animations.get_animation(Animation::CheckpointFlagIdle),
+        ) else {error!("Failed to find checkpoint flag animations"); continue;};
+        checkpoint.insert((out, ThenPlay(idle)));
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 use crate::animation::{Animation, Animations};
+use crate::interaction::PlayerInteraction;
 use bevy::{ecs::system::EntityCommands, prelude::*};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -45,6 +46,39 @@ pub enum SpawnType {
 }
 const MAX_RNG_TRYS: usize = 50;
 
+impl Collectable {
+    // spawns exactly this collectable without rolling a new position
+    pub fn spawn_at(
+        self,
+        pos: Vec3,
+        terrain: &Animations,
+        commands: &mut Commands,
+    ) -> Option<Entity> {
+        let Some(animation) = terrain.get_animation(self.collectable_type.into()) else {error!("Animation for {:?} not loaded", self.collectable_type); return None;};
+
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: Transform::from_translation(pos),
+                        rigid_body: RigidBody::Fixed,
+                        collider: Collider::ball(8.),
+                        item: self,
+                        ..Default::default()
+                    },
+
+                    Handle::<TextureAtlas>::default(),
+                    TextureAtlasSprite::default(),
+                    animation,
+                    Sensor,
+                    PlayerInteraction::Collect,
+                    Name::new("Collectable"),
+                ))
+                .id(),
+        )
+    }
+}
+
 impl MapObject for Collectable {
     fn spawn(
         &self,
@@ -59,7 +93,6 @@ impl MapObject for Collectable {
                 return None;
             }
             SpawnType::RandomRange(IVec2 { x: x0, y: y0 }, IVec2 { x: x1, y: y1 }) => {
-                let mut rng = rand::thread_rng();
                 let x_range = *x0.min(x1)..*x0.max(x1);
                 let y_range = *y0.min(y1)..*y0.max(y1);
                 let mut trys = 0;
@@ -69,8 +102,8 @@ impl MapObject for Collectable {
                         return None;
                     }
                     trys += 1;
-                    let x = rng.gen_range(x_range.clone());
-                    let y = rng.gen_range(y_range.clone());
+                    let x = map_data.rng().gen_range(x_range.clone());
+                    let y = map_data.rng().gen_range(y_range.clone());
                     if map_data.is_empty(IVec2 { x, y }) {
                         break Vec3::new(x as f32 * 16., y as f32 * 16., 1.);
                     }
@@ -81,7 +114,7 @@ impl MapObject for Collectable {
                     error!("No Random points given");
                     return None;
                 }
-                let IVec2 { x, y } = points[rand::thread_rng().gen_range(0..points.len())];
+                let IVec2 { x, y } = points[map_data.rng().gen_range(0..points.len())];
                 Vec3::new(x as f32 * 16., y as f32 * 16., 1.)
             }
             SpawnType::Fixed(IVec2 { x, y }) => {
@@ -112,7 +145,7 @@ impl MapObject for Collectable {
                 } else if points.len() == 1 {
                     set_none = true;
                 }
-                let index = rand::thread_rng().gen_range(0..points.len());
+                let index = map_data.rng().gen_range(0..points.len());
                 let IVec2 { x, y } = points.remove(index);
                 Vec3::new(x as f32 * 16., y as f32 * 16., 1.)
             }
@@ -122,27 +155,7 @@ impl MapObject for Collectable {
         if set_none {
             new_self.spawn_type = SpawnType::None;
         }
-        let Some(animation) = terrain.get_animation(self.collectable_type.into()) else {error!("Animation for {:?} not loaded", self.collectable_type); return None;};
-
-        Some(
-            commands
-                .spawn((
-                    CellBundle {
-                        transform: Transform::from_translation(pos),
-                        rigid_body: RigidBody::Fixed,
-                        collider: Collider::ball(8.),
-                        item: new_self,
-                        ..Default::default()
-                    },
-
-                    Handle::<TextureAtlas>::default(),
-                    TextureAtlasSprite::default(),
-                    animation,
-                    Sensor,
-                    Name::new("Collectable"),
-                ))
-                .id(),
-        )
+        new_self.spawn_at(pos, terrain, commands)
     }
     fn object_type(&self) -> super::levels::MapObjectType {
         super::levels::MapObjectType::Collectable
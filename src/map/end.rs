@@ -0,0 +1,91 @@
+use super::*;
+use crate::animation::{Animation, Animations, SpriteAnimation};
+use crate::ghost::GoalReached;
+use crate::interaction::{PlayerContact, PlayerInteraction};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// the trophy that finishes the level when touched
+#[derive(Component, Clone, Copy, Deserialize, Serialize, Reflect, Default)]
+pub struct End {
+    pub position: IVec2,
+}
+
+impl MapObject for End {
+    fn spawn(
+        &self,
+        terrain: &Animations,
+        commands: &mut Commands,
+        map_data: &mut MapData,
+    ) -> Option<Entity> {
+        let Some(animation) = terrain.get_animation(Animation::EndIdle) else {error!("Animation for End not loaded"); return None;};
+        self.set_full(map_data);
+        // the sprite is 64x64 with the trophy sat at the bottom
+        let pos = (self.position * 16).as_vec2() + Vec2::Y * 24.;
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: Transform::from_translation(pos.extend(1.)),
+                        rigid_body: RigidBody::Fixed,
+                        collider: Collider::cuboid(16., 24.),
+                        item: *self,
+                        ..Default::default()
+                    },
+                    Handle::<TextureAtlas>::default(),
+                    TextureAtlasSprite::default(),
+                    animation,
+                    Sensor,
+                    PlayerInteraction::Trigger,
+                    Name::new("End"),
+                ))
+                .id(),
+        )
+    }
+    fn object_type(&self) -> super::levels::MapObjectType {
+        super::levels::MapObjectType::End
+    }
+    fn serialize(&self) -> bevy::reflect::serde::Serializable {
+        bevy::reflect::serde::Serializable::Borrowed(self)
+    }
+    fn clone(&self) -> Box<dyn MapObject> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+    fn set_full(&self, map: &mut MapData) {
+        map.set_full(self.position);
+    }
+}
+
+impl DrawProps for End {
+    fn draw_props(_root: Entity) -> belly::core::eml::Eml {
+        use belly::prelude::*;
+        eml!(<label {_root} value="End Not done"/>)
+    }
+    fn ui_draw(_editor: Entity) -> belly::core::eml::Eml {
+        use belly::prelude::*;
+        eml!(<label {_editor} value="End Not done"/>)
+    }
+}
+
+// the goal only counts once, the level is respawned for the next attempt
+#[derive(Component)]
+struct Reached;
+
+pub(super) fn touch_end(
+    mut commands: Commands,
+    mut contacts: EventReader<PlayerContact>,
+    mut ends: Query<&mut Handle<SpriteAnimation>, (With<End>, Without<Reached>)>,
+    animations: Res<Animations>,
+    mut goal: EventWriter<GoalReached>,
+) {
+    for contact in contacts.iter() {
+        if !contact.began(PlayerInteraction::Trigger) {
+            continue;
+        }
+        let Ok(mut animation) = ends.get_mut(contact.object) else {continue;};
+        goal.send(GoalReached);
+        commands.entity(contact.object).insert(Reached);
+        let Some(pressed) = animations.get_animation(Animation::EndPressed) else {error!("Failed to find animation: EndPressed"); continue;};
+        *animation = pressed;
+    }
+}
@@ -0,0 +1,330 @@
+use super::*;
+use super::platform::{standing_on, PLATFORM_GROUP};
+use crate::animation::{Animation, Animations, SpriteAnimation};
+use crate::ghost::{PlayClock, PlayerInputs, Rewind, Rewound};
+use crate::player::{GroundedCheck, RealPlayer};
+use crate::speedrun::tick_seconds;
+use belly::{build::widget, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// seconds it shakes for before it drops
+const SHAKE_TIME: f32 = 0.5;
+
+// drops once it has been stood on for a while, then comes back
+#[derive(Component, Clone, Copy, Deserialize, Serialize, Reflect)]
+pub struct FallingPlatform {
+    pub position: IVec2,
+    // seconds stood on before it starts to shake
+    pub delay: f32,
+    // seconds after falling that it is put back
+    pub respawn: f32,
+}
+
+impl Default for FallingPlatform {
+    fn default() -> Self {
+        FallingPlatform {
+            position: IVec2::ZERO,
+            delay: 0.5,
+            respawn: 3.,
+        }
+    }
+}
+
+// attempt ticks spent in each state, reset with the rest of the map on reload
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FallState {
+    Idle(usize),
+    Shaking(usize),
+    Falling(usize),
+}
+
+impl Default for FallState {
+    fn default() -> Self {
+        FallState::Idle(0)
+    }
+}
+
+impl FallState {
+    fn step(self, platform: &FallingPlatform, stood: bool, dt: f32) -> FallState {
+        let seconds = |t: usize| t as f32 * dt;
+        match self {
+            // stepping off starts the count again
+            FallState::Idle(_) if !stood => FallState::Idle(0),
+            FallState::Idle(t) if seconds(t) >= platform.delay => FallState::Shaking(0),
+            FallState::Idle(t) => FallState::Idle(t + 1),
+            FallState::Shaking(t) if seconds(t) >= SHAKE_TIME => FallState::Falling(0),
+            FallState::Shaking(t) => FallState::Shaking(t + 1),
+            FallState::Falling(t) if seconds(t) >= platform.respawn => FallState::Idle(0),
+            FallState::Falling(t) => FallState::Falling(t + 1),
+        }
+    }
+
+    // true if next is just this a tick later, so it can be worked back to from next
+    fn continues(self, next: FallState) -> bool {
+        match (self, next) {
+            (FallState::Idle(0), FallState::Idle(0)) => true,
+            (FallState::Idle(a), FallState::Idle(b))
+            | (FallState::Shaking(a), FallState::Shaking(b))
+            | (FallState::Falling(a), FallState::Falling(b)) => b == a + 1,
+            _ => false,
+        }
+    }
+
+    // as it was ticks ago, as long as it has not changed state since
+    fn back(self, ticks: usize) -> FallState {
+        match self {
+            FallState::Idle(t) => FallState::Idle(t.saturating_sub(ticks)),
+            FallState::Shaking(t) => FallState::Shaking(t.saturating_sub(ticks)),
+            FallState::Falling(t) => FallState::Falling(t.saturating_sub(ticks)),
+        }
+    }
+
+    // pixels from where it was placed
+    fn offset(self, gravity: Vec2, dt: f32) -> Vec2 {
+        match self {
+            FallState::Idle(_) => Vec2::ZERO,
+            FallState::Shaking(t) => Vec2::X * (t as f32 * dt * 60.).sin(),
+            FallState::Falling(t) => gravity * (t as f32 * dt).powi(2) / 2.,
+        }
+    }
+}
+
+// the state as it was on the frame it changed, rewind goes back to the first one it passes
+#[derive(Component, Default)]
+pub struct FallHistory(Vec<(usize, FallState)>);
+
+impl MapObject for FallingPlatform {
+    fn spawn(
+        &self,
+        terrain: &Animations,
+        commands: &mut Commands,
+        map_data: &mut MapData,
+    ) -> Option<Entity> {
+        let Some(animation) = terrain.get_animation(Animation::FallingOn) else {error!("Animation for FallingPlatform not loaded"); return None;};
+        self.set_full(map_data);
+        let pos = (self.position * 16).as_vec2();
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: Transform::from_translation(pos.extend(1.)),
+                        rigid_body: RigidBody::KinematicPositionBased,
+                        collider: Collider::cuboid(16., 5.),
+                        item: *self,
+                        ..Default::default()
+                    },
+                    CollisionGroups::new(PLATFORM_GROUP, Group::ALL),
+                    Handle::<TextureAtlas>::default(),
+                    TextureAtlasSprite::default(),
+                    animation,
+                    FallState::default(),
+                    FallHistory::default(),
+                    Name::new("Falling Platform"),
+                ))
+                .id(),
+        )
+    }
+    fn object_type(&self) -> super::levels::MapObjectType {
+        super::levels::MapObjectType::FallingPlatform
+    }
+    fn serialize(&self) -> bevy::reflect::serde::Serializable {
+        bevy::reflect::serde::Serializable::Borrowed(self)
+    }
+    fn clone(&self) -> Box<dyn MapObject> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+    fn set_full(&self, map: &mut MapData) {
+        map.set_full(self.position);
+    }
+}
+
+impl DrawProps for FallingPlatform {
+    fn ui_draw(self_entity: Entity) -> belly::core::eml::Eml {
+        eml! {
+            <button entity=self_entity c:icon on:press=run!(|ctx| {
+                ctx.commands().add(|world: &mut World| {
+                    world.send_event(MapEvent::spawn(FallingPlatform::default()));
+                });
+            })>
+            <img src="Traps/Falling Platforms/Off.png"/>
+            </button>
+        }
+    }
+    fn draw_props(root: Entity) -> belly::core::eml::Eml {
+        eml!(<falling_platform root=root/>)
+    }
+}
+
+#[widget]
+fn falling_platform(ctx: &mut belly::build::WidgetContext) {
+    let Some(root) = ctx.required_param::<Entity>("root") else { return };
+    ctx.render(eml! {
+        <div id="editor">
+        <label value="Falling Platform"/>
+        <button on:press=run!(for root |data: &mut FallingPlatform| {
+            data.delay = (data.delay - 0.25).max(0.);
+        })>"-"</button>
+        <label bind:value=from!(root, FallingPlatform:delay|fmt.c("delay {}", c))/>
+        <button on:press=run!(for root |data: &mut FallingPlatform| {
+            data.delay += 0.25;
+        })>"+"</button>
+        <button on:press=run!(for root |data: &mut FallingPlatform| {
+            data.respawn = (data.respawn - 1.).max(1.);
+        })>"-"</button>
+        <label bind:value=from!(root, FallingPlatform:respawn|fmt.c("respawn {}", c))/>
+        <button on:press=run!(for root |data: &mut FallingPlatform| {
+            data.respawn += 1.;
+        })>"+"</button>
+        </div>
+    });
+}
+
+// stepped once per attempt tick, only the player drops them
+pub fn update_falling_platforms(
+    mut platforms: Query<(
+        Entity,
+        &mut Transform,
+        &FallingPlatform,
+        &mut FallState,
+        &mut FallHistory,
+        &mut Handle<SpriteAnimation>,
+    )>,
+    mut riders: Query<
+        (Entity, &mut Transform, &mut GroundedCheck),
+        (With<RealPlayer>, Without<FallingPlatform>),
+    >,
+    mut last: Local<usize>,
+    clock: Res<PlayClock>,
+    rewind: Res<Rewind>,
+    inputs: Res<PlayerInputs>,
+    rapier_config: Res<RapierConfiguration>,
+    rapier_context: Res<RapierContext>,
+    animations: Res<Animations>,
+) {
+    let dt = tick_seconds(&rapier_config);
+    let tick = clock.attempt_ticks();
+    let ticks = tick.saturating_sub(*last);
+    *last = tick;
+    // they hold still while the player scrubs, and restore_falling_platforms puts them back after
+    if rewind.target().is_some() {
+        return;
+    }
+    let standing: Vec<(Entity, Entity)> = riders
+        .iter()
+        .filter_map(|(rider, transform, _)| {
+            let hit = standing_on(&rapier_context, transform)?;
+            platforms.contains(hit).then_some((rider, hit))
+        })
+        .collect();
+    for (entity, mut transform, platform, mut state, mut history, mut animation) in &mut platforms {
+        let stood = standing.iter().any(|(_, on)| *on == entity);
+        for _ in 0..ticks {
+            let next = state.step(platform, stood, dt);
+            if !state.continues(next) {
+                history.0.push((inputs.frame(), *state));
+            }
+            *state = next;
+        }
+        let look = match *state {
+            FallState::Falling(_) => Animation::FallingOff,
+            _ => Animation::FallingOn,
+        };
+        if let Some(handle) = animations.get_animation(look) {
+            if *animation != handle {
+                *animation = handle;
+            }
+        }
+
+        let pos = (platform.position * 16).as_vec2() + state.offset(rapier_config.gravity, dt);
+        let delta = pos - transform.translation.truncate();
+        // kinematic, so this is where rapier moves it to on the next step
+        transform.translation = pos.extend(transform.translation.z);
+        // shaking stays under the rider's feet, and respawning leaves them behind
+        let FallState::Falling(_) = *state else {continue;};
+        for (rider, _) in standing.iter().filter(|(_, on)| *on == entity) {
+            let Ok((_, mut transform, mut check)) = riders.get_mut(*rider) else {continue;};
+            transform.translation += delta.extend(0.);
+            check.carry(delta);
+        }
+    }
+}
+
+// like restore_collectables, goes back to the state they were in on the frame rewound to
+pub fn restore_falling_platforms(
+    mut rewound: EventReader<Rewound>,
+    mut platforms: Query<(&mut FallState, &mut FallHistory)>,
+) {
+    for Rewound { frame, back } in rewound.iter() {
+        for (mut state, mut history) in &mut platforms {
+            *state = match history.0.iter().position(|(changed, _)| changed >= frame) {
+                Some(index) => {
+                    let (changed, before) = history.0[index];
+                    history.0.truncate(index);
+                    before.back(changed - frame)
+                }
+                None => state.back(*back),
+            };
+        }
+    }
+}
+
+#[test]
+fn falling_platforms_drop_and_come_back() {
+    let platform = FallingPlatform {
+        position: IVec2::ZERO,
+        delay: 0.5,
+        respawn: 1.,
+    };
+    let dt = 0.25;
+    let mut state = FallState::default();
+    state = state.step(&platform, true, dt);
+    state = state.step(&platform, true, dt);
+    // stepping off before the delay is up keeps it in place
+    state = state.step(&platform, false, dt);
+    assert_eq!(state, FallState::Idle(0));
+    for _ in 0..3 {
+        state = state.step(&platform, true, dt);
+    }
+    assert_eq!(state, FallState::Shaking(0));
+    // once it shakes it drops whether or not anyone is still on it
+    for _ in 0..3 {
+        state = state.step(&platform, false, dt);
+    }
+    assert_eq!(state, FallState::Falling(0));
+    let falling = state.step(&platform, false, dt);
+    assert!(falling.offset(Vec2::NEG_Y * 294., dt).y < 0.);
+    assert!(state.continues(falling));
+    for _ in 0..5 {
+        state = state.step(&platform, false, dt);
+    }
+    assert_eq!(state, FallState::Idle(0));
+}
+
+#[test]
+fn rewound_falling_platforms_restore_their_state() {
+    let mut app = App::new();
+    app.add_event::<Rewound>()
+        .add_systems(Update, restore_falling_platforms);
+    // started shaking on frame 20 and has been falling since frame 30
+    let platform = app
+        .world
+        .spawn((
+            FallState::Falling(5),
+            FallHistory(vec![(20, FallState::Idle(10)), (30, FallState::Shaking(9))]),
+        ))
+        .id();
+
+    app.world.send_event(Rewound { frame: 32, back: 3 });
+    app.update();
+    assert_eq!(app.world.get::<FallState>(platform), Some(&FallState::Falling(2)));
+
+    app.world.send_event(Rewound { frame: 25, back: 7 });
+    app.update();
+    assert_eq!(app.world.get::<FallState>(platform), Some(&FallState::Shaking(4)));
+
+    app.world.send_event(Rewound { frame: 15, back: 10 });
+    app.update();
+    assert_eq!(app.world.get::<FallState>(platform), Some(&FallState::Idle(5)));
+    assert!(app.world.get::<FallHistory>(platform).unwrap().0.is_empty());
+}
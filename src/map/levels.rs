@@ -5,18 +5,42 @@ use bevy::{
     reflect::{TypePath, TypeUuid},
 };
 use bincode::Options;
+use crate::ghost::GhostPreset;
 use serde::{
     de::{DeserializeSeed, Visitor},
     Deserialize, Serialize,
 };
 
-const CURRENT_VERSION: u8 = 0;
+const CURRENT_VERSION: u8 = 2;
 
 #[derive(TypeUuid, Default, TypePath)]
 #[uuid = "e6b53f1c-9471-465c-b411-7729177acb9e"]
 pub struct Level {
     pub player_start: IVec2,
     pub objects: Vec<Box<dyn MapObject>>,
+    pub meta: LevelMeta,
+}
+
+// settings for the level that are not part of the map itself
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelMeta {
+    #[serde(default)]
+    pub ghost_preset: Option<GhostPreset>,
+    // path to the track to loop while the level is played
+    #[serde(default)]
+    pub music: Option<String>,
+}
+
+// meta as version 1 codes stored it, before music
+#[derive(Deserialize)]
+struct LevelMetaV1 {
+    ghost_preset: Option<GhostPreset>,
+}
+
+impl LevelMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == LevelMeta::default()
+    }
 }
 
 impl Level {
@@ -27,14 +51,54 @@ impl Level {
             0 => Ok(bincode::options()
                 .with_varint_encoding()
                 .deserialize(&bytes[1..])?),
+            1 | 2 => {
+                let mut data = &bytes[1..];
+                let mut level: Level = bincode::options()
+                    .with_varint_encoding()
+                    .deserialize_from(&mut data)?;
+                level.meta = if *version == 1 {
+                    let meta: LevelMetaV1 = bincode::options()
+                        .with_varint_encoding()
+                        .deserialize_from(&mut data)?;
+                    LevelMeta {
+                        ghost_preset: meta.ghost_preset,
+                        ..Default::default()
+                    }
+                } else {
+                    bincode::options()
+                        .with_varint_encoding()
+                        .deserialize_from(&mut data)?
+                };
+                Ok(level)
+            }
             _ => Err(anyhow::anyhow!("Unsuported version: {}", version)),
         }
     }
+    // FNV-1a over the bincode data so the hash is the same between builds
+    pub fn content_hash(&self) -> Result<u64, bincode::Error> {
+        let bytes = bincode::options()
+            .with_varint_encoding()
+            .serialize(&self)?;
+        Ok(bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        }))
+    }
     pub fn to_base64(&self) -> Result<String, bincode::Error> {
+        // levels without meta stay version 0 so old codes don't change
+        if self.meta.is_empty() {
+            let mut bytes = vec![0];
+            bincode::options()
+                .with_varint_encoding()
+                .serialize_into(&mut bytes, &self)?;
+            return Ok(base64::encode(bytes));
+        }
         let mut bytes = vec![CURRENT_VERSION];
         bincode::options()
             .with_varint_encoding()
             .serialize_into(&mut bytes, &self)?;
+        bincode::options()
+            .with_varint_encoding()
+            .serialize_into(&mut bytes, &self.meta)?;
         Ok(base64::encode(bytes))
     }
 }
@@ -67,6 +131,7 @@ impl<'de> Deserialize<'de> for Level {
 pub enum LevelFields {
     Start,
     Objects,
+    Meta,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -75,6 +140,12 @@ pub enum MapObjectType {
     Empty,
     Box,
     Collectable,
+    End,
+    Checkpoint,
+    Spikes,
+    Saw,
+    MovingPlatform,
+    FallingPlatform,
 }
 
 struct LevelVisitor;
@@ -91,6 +162,7 @@ impl<'de> Visitor<'de> for LevelVisitor {
         let mut data = Level {
             player_start: IVec2::ZERO,
             objects: Vec::new(),
+            meta: LevelMeta::default(),
         };
         while let Some(key) = map.next_key::<LevelFields>()? {
             match key {
@@ -100,6 +172,9 @@ impl<'de> Visitor<'de> for LevelVisitor {
                 LevelFields::Objects => {
                     data.objects = map.next_value_seed(ObjectsVisitor)?;
                 }
+                LevelFields::Meta => {
+                    data.meta = map.next_value::<LevelMeta>()?;
+                }
             }
         }
         Ok(data)
@@ -115,6 +190,7 @@ impl<'de> Visitor<'de> for LevelVisitor {
             objects: seq
                 .next_element_seed(ObjectsVisitor)?
                 .ok_or(serde::de::Error::missing_field("Objects"))?,
+            meta: LevelMeta::default(),
         })
     }
 }
@@ -150,6 +226,24 @@ impl<'de> Visitor<'de> for ObjectsVisitor {
                 MapObjectType::Collectable => {
                     objects.push(Box::new(map.next_value::<Collectable>()?));
                 }
+                MapObjectType::End => {
+                    objects.push(Box::new(map.next_value::<End>()?));
+                }
+                MapObjectType::Checkpoint => {
+                    objects.push(Box::new(map.next_value::<Checkpoint>()?));
+                }
+                MapObjectType::Spikes => {
+                    objects.push(Box::new(map.next_value::<Spikes>()?));
+                }
+                MapObjectType::Saw => {
+                    objects.push(Box::new(map.next_value::<Saw>()?));
+                }
+                MapObjectType::MovingPlatform => {
+                    objects.push(Box::new(map.next_value::<MovingPlatform>()?));
+                }
+                MapObjectType::FallingPlatform => {
+                    objects.push(Box::new(map.next_value::<FallingPlatform>()?));
+                }
             }
         }
         Ok(objects)
@@ -186,9 +280,14 @@ impl Serialize for Level {
         S: serde::Serializer,
     {
         use ::serde::ser::SerializeStruct;
-        let mut struct_data = serializer.serialize_struct("Level", 2)?;
+        // binary formats get the meta appended separately, see to_base64
+        let with_meta = serializer.is_human_readable() && !self.meta.is_empty();
+        let mut struct_data = serializer.serialize_struct("Level", if with_meta { 3 } else { 2 })?;
         struct_data.serialize_field("start", &self.player_start)?;
         struct_data.serialize_field("objects", &ObjectsSerializer(&self.objects))?;
+        if with_meta {
+            struct_data.serialize_field("meta", &self.meta)?;
+        }
         struct_data.end()
     }
 }
@@ -231,6 +330,7 @@ fn test_serialize_level() {
                 spawn_type: SpawnType::Fixed(IVec2 { x: 5, y: 5 }),
             }),
         ],
+        ..Default::default()
     };
     assert_eq!(
         include_str!("test.lvl.ron"),
@@ -262,3 +362,45 @@ fn bincode_serde() {
     let de = Level::from_base64(&ser).expect("To Get level from str");
     assert!(level == de);
 }
+
+#[test]
+fn bincode_meta() {
+    let level = Level {
+        player_start: IVec2::new(0, 0),
+        objects: vec![Box::new(Square {
+            offset: IVec3 { x: 10, y: 4, z: 0 },
+            size: IVec2::splat(1),
+            material: TerrainMaterial::Gold,
+        })],
+        meta: LevelMeta {
+            ghost_preset: Some(GhostPreset::Hard),
+            music: Some("Audio/level.ogg".to_string()),
+        },
+    };
+    let ser = level.to_base64().expect("To base64 to work");
+    let de = Level::from_base64(&ser).expect("To Get level from str");
+    assert!(level == de);
+    assert_eq!(de.meta, level.meta);
+}
+
+#[test]
+fn bincode_meta_v1() {
+    let level = Level {
+        player_start: IVec2::new(0, 0),
+        objects: Vec::new(),
+        meta: LevelMeta::default(),
+    };
+    // version 1 codes stop after the ghost preset
+    let mut bytes = vec![1];
+    bincode::options()
+        .with_varint_encoding()
+        .serialize_into(&mut bytes, &level)
+        .expect("level to serialize");
+    bincode::options()
+        .with_varint_encoding()
+        .serialize_into(&mut bytes, &Some(GhostPreset::Hard))
+        .expect("meta to serialize");
+    let de = Level::from_base64(&base64::encode(bytes)).expect("version 1 code to load");
+    assert_eq!(de.meta.ghost_preset, Some(GhostPreset::Hard));
+    assert_eq!(de.meta.music, None);
+}
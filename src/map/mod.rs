@@ -1,16 +1,30 @@
 use bevy::{prelude::*, reflect::TypePath};
 use bevy_rapier2d::prelude::*;
 
+mod checkpoint;
 mod collectable;
+mod end;
+mod falling;
 mod levels;
+mod pack;
+mod platform;
+mod saw;
+mod spikes;
 mod square;
 mod tile_map;
 
 mod prelude {
     pub use super::MapItem;
     use super::*;
+    pub use checkpoint::{Checkpoint, CheckpointReached};
     pub use collectable::{Collectable, CollectableType, SpawnType};
-    pub use levels::Level;
+    pub use end::End;
+    pub use falling::FallingPlatform;
+    pub use levels::{Level, LevelMeta};
+    pub use pack::{CurrentPack, LevelPack};
+    pub use platform::MovingPlatform;
+    pub use saw::{Saw, SawMode, SawPath};
+    pub use spikes::{SpikeDirection, Spikes};
     pub use square::Square;
     pub use tile_map::{MapData, MapEvent, MapObject, TerrainMaterial, TerrainType};
 }
@@ -24,11 +38,36 @@ impl bevy::prelude::Plugin for MapPlugin {
             .init_resource::<MapData>()
             .add_asset::<levels::Level>()
             .add_asset_loader(levels::LevelLoader)
+            .add_asset::<LevelPack>()
+            .add_asset_loader(pack::LevelPackLoader)
+            .init_resource::<CurrentPack>()
             .init_resource::<LoadedLevel>()
+            .init_resource::<LevelHash>()
+            .add_event::<AttemptStarted>()
             .add_systems(Update, load_map)
             .register_type::<Square>()
+            .register_type::<Spikes>()
+            .register_type::<Saw>()
+            .register_type::<MovingPlatform>()
+            .register_type::<FallingPlatform>()
             .register_type::<TerrainMaterial>()
-            .add_systems(Last, square::update_square);
+            .add_systems(Last, (square::update_square, spikes::update_spikes))
+            .add_systems(Update, saw::move_saws)
+            // riders have moved by the time the platform carries them
+            .add_systems(Update, platform::move_platforms.after(PlayerStages::Move))
+            // after the player moves, so any rewind has finished and been restored
+            .add_systems(
+                Update,
+                (falling::restore_falling_platforms, falling::update_falling_platforms)
+                    .chain()
+                    .after(PlayerStages::Move)
+                    .run_if(not(is_paused)),
+            )
+            .add_event::<CheckpointReached>()
+            .add_systems(
+                Update,
+                (end::touch_end, checkpoint::touch_checkpoint).in_set(InteractionSet::Handle),
+            );
     }
 }
 
@@ -77,13 +116,26 @@ impl<T: MapObject + Default + DrawProps + Component> Default for CellBundle<T> {
 
 pub use prelude::*;
 
-use crate::{ghost::GhostEvents, player::RealPlayer};
+use crate::{
+    ghost::{GhostEvents, GhostPreset, GhostRules},
+    pause::is_paused,
+    interaction::InteractionSet,
+    player::{PlayerStages, RealPlayer},
+};
 
 use crate::editor::DrawProps;
 
 #[derive(Resource, Default)]
 pub struct LoadedLevel(pub Handle<Level>);
 
+// content hash of the level that is spawned, for anything saved per level
+#[derive(Resource, Default)]
+pub struct LevelHash(pub Option<u64>);
+
+// sent each time the level is spawned fresh, from a load, a restart or a death
+#[derive(Event)]
+pub struct AttemptStarted;
+
 #[derive(Component, TypePath)]
 pub struct MapItem(
     fn(root: Entity) -> belly::core::eml::Eml
@@ -98,14 +150,18 @@ impl MapItem {
     }
 }
 
-fn load_map(
+pub(crate) fn load_map(
     mut map_event: EventWriter<MapEvent>,
     levels: Res<Assets<Level>>,
     current_level: Res<LoadedLevel>,
     map_item: Query<Entity, With<MapItem>>,
     mut commands: Commands,
     mut events: EventWriter<GhostEvents>,
-    mut player: Query<&mut Transform, With<RealPlayer>>,
+    mut player: Query<(&mut Transform, &mut Velocity), With<RealPlayer>>,
+    mut rules: ResMut<GhostRules>,
+    preset: Res<GhostPreset>,
+    mut level_hash: ResMut<LevelHash>,
+    mut attempt: EventWriter<AttemptStarted>,
 ) {
     if !current_level.is_changed() {
         return;
@@ -113,12 +169,22 @@ fn load_map(
     let Some(level) = levels.get(&current_level.0) else {return;};
     events.send(GhostEvents::ClearGhosts);
     events.send(GhostEvents::ClearTrail);
-    let mut player = player.single_mut();
+    *rules = level.meta.ghost_preset.unwrap_or(*preset).rules();
+    level_hash.0 = match level.content_hash() {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            error!("Failed to hash level: {}", e);
+            None
+        }
+    };
+    let (mut player, mut velocity) = player.single_mut();
     player.translation = level.player_start.as_vec2().extend(0.0);
+    *velocity = Velocity::zero();
     for item in &map_item {
         commands.entity(item).despawn_recursive();
     }
     for obj in level.objects.iter() {
         map_event.send(MapEvent::Spawn(MapObject::clone(obj.as_ref())))
     }
+    attempt.send(AttemptStarted);
 }
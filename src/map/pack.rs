@@ -0,0 +1,66 @@
+use bevy::{
+    asset::{AssetLoader, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+};
+
+// levels played one after another, paths are relative to assets
+#[derive(TypeUuid, TypePath, Debug, Default)]
+#[uuid = "3c1f7d2a-6b84-4e0f-a9d3-51e2c7b80f64"]
+pub struct LevelPack(pub Vec<String>);
+
+#[derive(Default)]
+pub struct LevelPackLoader;
+
+impl AssetLoader for LevelPackLoader {
+    fn extensions(&self) -> &[&str] {
+        &["pack.ron"]
+    }
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let levels: Vec<String> = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(LevelPack(levels)));
+            Ok(())
+        })
+    }
+}
+
+impl LevelPack {
+    pub fn index_of(&self, path: &str) -> Option<usize> {
+        self.0.iter().position(|level| level == path)
+    }
+}
+
+// the pack being played and where in it the loaded level is,
+// None for levels that came from somewhere else
+#[derive(Resource, Default)]
+pub struct CurrentPack {
+    pub pack: Handle<LevelPack>,
+    pub index: Option<usize>,
+}
+
+impl CurrentPack {
+    pub fn next<'a>(&self, packs: &'a Assets<LevelPack>) -> Option<(usize, &'a str)> {
+        let next = self.index? + 1;
+        let path = packs.get(&self.pack)?.0.get(next)?;
+        Some((next, path))
+    }
+
+    // points index at path if the pack has it
+    pub fn select(&mut self, packs: &Assets<LevelPack>, path: &str) {
+        self.index = packs.get(&self.pack).and_then(|pack| pack.index_of(path));
+    }
+}
+
+#[test]
+fn pack_finds_levels() {
+    let pack = LevelPack(vec!["a.lvl.ron".into(), "b.lvl.ron".into()]);
+    assert_eq!(pack.index_of("b.lvl.ron"), Some(1));
+    assert_eq!(pack.index_of("c.lvl.ron"), None);
+    let levels: Vec<String> = ron::from_str("[\"a.lvl.ron\"]").expect("pack to parse");
+    assert_eq!(levels, vec!["a.lvl.ron"]);
+}
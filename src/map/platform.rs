@@ -0,0 +1,321 @@
+use super::*;
+use super::saw::{path_length, point_along};
+use crate::animation::{Animation, Animations, SpriteAnimation};
+use crate::ghost::{GhostTick, KinematicGhost, PlayClock, PlayerInputs, Rewind};
+use crate::player::{GroundedCheck, RealPlayer};
+use crate::speedrun::tick_seconds;
+use belly::{build::widget, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// ghosts don't collide with platforms, they are held up by where it was when they were recorded
+pub const PLATFORM_GROUP: Group = Group::GROUP_4;
+const HALF_HEIGHT: f32 = 4.;
+
+// goes back and forth along its path, waiting at each end
+#[derive(Component, Clone, Deserialize, Serialize, Reflect)]
+pub struct MovingPlatform {
+    pub position: IVec2,
+    // in cells from position
+    pub path: Vec<IVec2>,
+    // cells per second
+    pub speed: f32,
+    // seconds at each end
+    pub wait: f32,
+    // stays put until the player stands on it
+    pub triggered: bool,
+}
+
+impl Default for MovingPlatform {
+    fn default() -> Self {
+        MovingPlatform {
+            position: IVec2::ZERO,
+            path: vec![IVec2::new(4, 0)],
+            speed: 2.,
+            wait: 1.,
+            triggered: false,
+        }
+    }
+}
+
+impl MovingPlatform {
+    // pixels from position after running for seconds
+    pub fn offset(&self, seconds: f32) -> Vec2 {
+        let points: Vec<Vec2> = std::iter::once(Vec2::ZERO)
+            .chain(self.path.iter().map(|point| (*point * 16).as_vec2()))
+            .collect();
+        let length = path_length(&points);
+        let speed = self.speed * 16.;
+        if length <= 0. || speed <= 0. {
+            return Vec2::ZERO;
+        }
+        let wait = self.wait.max(0.);
+        let travel = length / speed;
+        let phase = seconds.rem_euclid((wait + travel) * 2.);
+        let along = if phase < wait {
+            0.
+        } else if phase < wait + travel {
+            (phase - wait) * speed
+        } else if phase < wait * 2. + travel {
+            length
+        } else {
+            length - (phase - wait * 2. - travel) * speed
+        };
+        point_along(&points, along)
+    }
+
+    // where it is tick ticks into the attempt, a triggered one waits until started
+    fn position_at(&self, started: Option<usize>, tick: usize, dt: f32) -> Vec2 {
+        let running = match self.triggered {
+            false => tick,
+            true => started
+                .filter(|start| *start <= tick)
+                .map_or(0, |start| tick - start),
+        };
+        (self.position * 16).as_vec2() + self.offset(running as f32 * dt)
+    }
+}
+
+// the attempt tick a triggered platform set off on
+#[derive(Component, Default)]
+pub struct PlatformStart(Option<usize>);
+
+impl MapObject for MovingPlatform {
+    fn spawn(
+        &self,
+        terrain: &Animations,
+        commands: &mut Commands,
+        map_data: &mut MapData,
+    ) -> Option<Entity> {
+        let Some(animation) = terrain.get_animation(Animation::PlatformOn) else {error!("Animation for MovingPlatform not loaded"); return None;};
+        self.set_full(map_data);
+        let pos = (self.position * 16).as_vec2();
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: Transform::from_translation(pos.extend(1.)),
+                        rigid_body: RigidBody::KinematicPositionBased,
+                        collider: Collider::cuboid(16., HALF_HEIGHT),
+                        item: <Self as Clone>::clone(self),
+                        ..Default::default()
+                    },
+                    CollisionGroups::new(PLATFORM_GROUP, Group::ALL),
+                    Handle::<TextureAtlas>::default(),
+                    TextureAtlasSprite::default(),
+                    animation,
+                    PlatformStart::default(),
+                    Name::new("Moving Platform"),
+                ))
+                .id(),
+        )
+    }
+    fn object_type(&self) -> super::levels::MapObjectType {
+        super::levels::MapObjectType::MovingPlatform
+    }
+    fn serialize(&self) -> bevy::reflect::serde::Serializable {
+        bevy::reflect::serde::Serializable::Borrowed(self)
+    }
+    fn clone(&self) -> Box<dyn MapObject> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+    fn set_full(&self, map: &mut MapData) {
+        map.set_full(self.position);
+    }
+}
+
+impl DrawProps for MovingPlatform {
+    fn ui_draw(self_entity: Entity) -> belly::core::eml::Eml {
+        eml! {
+            <button entity=self_entity c:icon on:press=run!(|ctx| {
+                ctx.commands().add(|world: &mut World| {
+                    world.send_event(MapEvent::spawn(MovingPlatform::default()));
+                });
+            })>
+            <img src="Traps/Platforms/Grey Off.png"/>
+            </button>
+        }
+    }
+    fn draw_props(root: Entity) -> belly::core::eml::Eml {
+        eml!(<moving_platform root=root/>)
+    }
+}
+
+#[widget]
+fn moving_platform(ctx: &mut belly::build::WidgetContext) {
+    let Some(root) = ctx.required_param::<Entity>("root") else { return };
+    ctx.render(eml! {
+        <div id="editor">
+        <label value="Moving Platform"/>
+        <button on:press=run!(for root |data: &mut MovingPlatform| {
+            data.speed = (data.speed - 0.5).max(0.5);
+        })>"-"</button>
+        <label bind:value=from!(root, MovingPlatform:speed|fmt.c("speed {}", c))/>
+        <button on:press=run!(for root |data: &mut MovingPlatform| {
+            data.speed += 0.5;
+        })>"+"</button>
+        <button on:press=run!(for root |data: &mut MovingPlatform| {
+            data.wait = (data.wait - 0.5).max(0.);
+        })>"-"</button>
+        <label bind:value=from!(root, MovingPlatform:wait|fmt.c("wait {}", c))/>
+        <button on:press=run!(for root |data: &mut MovingPlatform| {
+            data.wait += 0.5;
+        })>"+"</button>
+        <button on:press=run!(for root |data: &mut MovingPlatform| {
+            data.triggered = !data.triggered;
+        })>"triggered"</button>
+        <label bind:value=from!(root, MovingPlatform:triggered|fmt.c("{}", c))/>
+        </div>
+    });
+}
+
+// placed from the ticks into the attempt like saws, the player rides it with rapier,
+// ghosts are stood on it as it was on the tick their frame was recorded, set off
+// when it was set off in their recording
+pub fn move_platforms(
+    mut platforms: Query<(
+        Entity,
+        &mut Transform,
+        &MovingPlatform,
+        &mut PlatformStart,
+        &mut Handle<SpriteAnimation>,
+    )>,
+    mut riders: Query<
+        (Entity, &mut Transform, &mut GroundedCheck),
+        (With<RealPlayer>, Without<MovingPlatform>),
+    >,
+    mut ghosts: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut GroundedCheck,
+            &GhostTick,
+        ),
+        (
+            Without<KinematicGhost>,
+            Without<RealPlayer>,
+            Without<MovingPlatform>,
+        ),
+    >,
+    clock: Res<PlayClock>,
+    rewind: Res<Rewind>,
+    mut inputs: ResMut<PlayerInputs>,
+    rapier_config: Res<RapierConfiguration>,
+    rapier_context: Res<RapierContext>,
+    animations: Res<Animations>,
+) {
+    let dt = tick_seconds(&rapier_config);
+    let tick = clock.attempt_ticks();
+    // (rider, platform) from where everything was last step
+    let standing: Vec<(Entity, Entity)> = riders
+        .iter()
+        .filter_map(|(rider, transform, _)| {
+            let hit = standing_on(&rapier_context, transform)?;
+            platforms.contains(hit).then_some((rider, hit))
+        })
+        .collect();
+    for (entity, mut transform, platform, mut start, mut animation) in &mut platforms {
+        let stood = standing.iter().any(|(_, on)| *on == entity);
+        if platform.triggered && start.0.is_none() && stood {
+            start.0 = Some(tick);
+        }
+        if platform.triggered {
+            let frame = inputs.frame();
+            inputs.mark_trigger(frame, platform.position, start.0);
+        }
+        let state = match !platform.triggered || start.0.is_some() {
+            true => Animation::PlatformOn,
+            false => Animation::PlatformOff,
+        };
+        if let Some(handle) = animations.get_animation(state) {
+            if *animation != handle {
+                *animation = handle;
+            }
+        }
+
+        let pos = platform.position_at(start.0, tick, dt);
+        let delta = pos - transform.translation.truncate();
+        // kinematic, so this is where rapier moves it to on the next step
+        transform.translation = pos.extend(transform.translation.z);
+        // friction carries the player sideways and the platform pushes them up,
+        // going down they are kept on it so they don't drop a little every step
+        if rewind.target().is_none() && delta.length() <= 8. {
+            for (rider, _) in standing.iter().filter(|(_, on)| *on == entity) {
+                let Ok((_, mut transform, mut check)) = riders.get_mut(*rider) else {continue;};
+                transform.translation.y += delta.y;
+                check.carry(Vec2::Y * delta.y);
+            }
+        }
+
+        for (mut ghost, mut velocity, mut check, ghost_tick) in &mut ghosts {
+            let Some(then) = ghost_tick.tick else {continue;};
+            // shared runs weren't recorded here, they see it as the player set it off
+            let started = match ghost_tick.frame {
+                Some(frame) => inputs.trigger_at(frame, platform.position),
+                None => start.0,
+            };
+            let at = platform.position_at(started, then, dt);
+            let top = at.y + HALF_HEIGHT;
+            let feet = ghost.translation.y - 16.;
+            // only catches a ghost that was landing on it or stood on it
+            if (ghost.translation.x - at.x).abs() > 16. + 9.
+                || velocity.linvel.y > 0.
+                || feet < top - HALF_HEIGHT
+                || feet > top + 1.
+            {
+                continue;
+            }
+            ghost.translation.y = top + 16.;
+            velocity.linvel.y = 0.;
+            let before = platform.position_at(started, then.saturating_sub(1), dt);
+            check.carry(at - before);
+        }
+    }
+}
+
+// what is right under a rider's feet
+pub(super) fn standing_on(rapier_context: &RapierContext, rider: &Transform) -> Option<Entity> {
+    let feet = rider.translation.truncate() - Vec2::Y * 15.9;
+    rapier_context
+        .cast_ray(
+            feet,
+            Vec2::NEG_Y,
+            1.,
+            true,
+            QueryFilter::exclude_dynamic().exclude_sensors(),
+        )
+        .map(|(hit, _)| hit)
+}
+
+#[test]
+fn platforms_wait_at_each_end() {
+    let platform = MovingPlatform {
+        path: vec![IVec2::new(2, 0)],
+        speed: 1.,
+        wait: 1.,
+        ..Default::default()
+    };
+    assert_eq!(platform.offset(0.5), Vec2::ZERO);
+    assert_eq!(platform.offset(2.), Vec2::new(16., 0.));
+    assert_eq!(platform.offset(3.5), Vec2::new(32., 0.));
+    assert_eq!(platform.offset(5.), Vec2::new(16., 0.));
+    assert_eq!(platform.offset(6.5), Vec2::ZERO);
+}
+
+#[test]
+fn triggered_platforms_wait_to_be_stood_on() {
+    let platform = MovingPlatform {
+        path: vec![IVec2::new(2, 0)],
+        speed: 1.,
+        wait: 0.,
+        triggered: true,
+        ..Default::default()
+    };
+    assert_eq!(platform.position_at(None, 60, 1. / 60.), Vec2::ZERO);
+    assert_eq!(
+        platform.position_at(Some(60), 120, 1. / 60.),
+        Vec2::new(16., 0.)
+    );
+    // a ghost recorded before it set off still sees it waiting
+    assert_eq!(platform.position_at(Some(60), 30, 1. / 60.), Vec2::ZERO);
+}
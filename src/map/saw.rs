@@ -0,0 +1,253 @@
+use super::*;
+use crate::animation::{Animation, Animations};
+use crate::ghost::PlayClock;
+use crate::interaction::PlayerInteraction;
+use crate::speedrun::tick_seconds;
+use belly::{build::widget, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// what the saw does at the end of its path
+#[derive(Clone, Copy, Deserialize, Serialize, Reflect, Default, Debug, PartialEq, Eq)]
+pub enum SawMode {
+    #[default]
+    PingPong,
+    Loop,
+}
+
+impl SawMode {
+    // how far along a path of length the saw is after travelling distance
+    fn wrap(self, distance: f32, length: f32) -> f32 {
+        match self {
+            SawMode::Loop => distance.rem_euclid(length),
+            SawMode::PingPong => {
+                let along = distance.rem_euclid(length * 2.);
+                if along > length {
+                    length * 2. - along
+                } else {
+                    along
+                }
+            }
+        }
+    }
+
+    fn toggle(self) -> SawMode {
+        match self {
+            SawMode::PingPong => SawMode::Loop,
+            SawMode::Loop => SawMode::PingPong,
+        }
+    }
+}
+
+// in cells, waypoints are from the saw's position and a circle is around it
+#[derive(Clone, Deserialize, Serialize, Reflect, Debug, PartialEq)]
+pub enum SawPath {
+    Waypoints(Vec<IVec2>),
+    Circle { radius: f32 },
+}
+
+impl Default for SawPath {
+    fn default() -> Self {
+        SawPath::Waypoints(vec![IVec2::new(4, 0)])
+    }
+}
+
+// a blade that runs along its path, kills the player on touch
+#[derive(Component, Clone, Deserialize, Serialize, Reflect, Default)]
+pub struct Saw {
+    pub position: IVec2,
+    pub path: SawPath,
+    // cells per second
+    pub speed: f32,
+    pub mode: SawMode,
+}
+
+impl Saw {
+    // pixels from position after running for seconds
+    pub fn offset(&self, seconds: f32) -> Vec2 {
+        let distance = seconds * self.speed * 16.;
+        match &self.path {
+            SawPath::Circle { radius } => {
+                let radius = radius * 16.;
+                if radius <= 0. {
+                    return Vec2::ZERO;
+                }
+                let angle = self.mode.wrap(distance, std::f32::consts::TAU * radius) / radius;
+                Vec2::new(angle.cos(), angle.sin()) * radius
+            }
+            SawPath::Waypoints(waypoints) => {
+                let mut points: Vec<Vec2> = std::iter::once(Vec2::ZERO)
+                    .chain(waypoints.iter().map(|point| (*point * 16).as_vec2()))
+                    .collect();
+                if self.mode == SawMode::Loop {
+                    points.push(Vec2::ZERO);
+                }
+                let length = path_length(&points);
+                if length <= 0. {
+                    return Vec2::ZERO;
+                }
+                point_along(&points, self.mode.wrap(distance, length))
+            }
+        }
+    }
+}
+
+pub(super) fn path_length(points: &[Vec2]) -> f32 {
+    points.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+// the point along pixels into the path, clamped to its ends
+pub(super) fn point_along(points: &[Vec2], mut along: f32) -> Vec2 {
+    for w in points.windows(2) {
+        let step = w[0].distance(w[1]);
+        if step > 0. && along <= step {
+            return w[0].lerp(w[1], along.max(0.) / step);
+        }
+        along -= step;
+    }
+    points.last().copied().unwrap_or_default()
+}
+
+impl MapObject for Saw {
+    fn spawn(
+        &self,
+        terrain: &Animations,
+        commands: &mut Commands,
+        map_data: &mut MapData,
+    ) -> Option<Entity> {
+        let Some(animation) = terrain.get_animation(Animation::Saw) else {error!("Animation for Saw not loaded"); return None;};
+        self.set_full(map_data);
+        let pos = (self.position * 16).as_vec2();
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: Transform::from_translation(pos.extend(1.)),
+                        rigid_body: RigidBody::KinematicPositionBased,
+                        collider: Collider::ball(16.),
+                        item: <Self as Clone>::clone(self),
+                        ..Default::default()
+                    },
+                    Handle::<TextureAtlas>::default(),
+                    TextureAtlasSprite::default(),
+                    animation,
+                    Sensor,
+                    PlayerInteraction::Hurt,
+                    Name::new("Saw"),
+                ))
+                .id(),
+        )
+    }
+    fn object_type(&self) -> super::levels::MapObjectType {
+        super::levels::MapObjectType::Saw
+    }
+    fn serialize(&self) -> bevy::reflect::serde::Serializable {
+        bevy::reflect::serde::Serializable::Borrowed(self)
+    }
+    fn clone(&self) -> Box<dyn MapObject> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+    fn set_full(&self, map: &mut MapData) {
+        map.set_full(self.position);
+    }
+}
+
+impl DrawProps for Saw {
+    fn ui_draw(self_entity: Entity) -> belly::core::eml::Eml {
+        eml! {
+            <button entity=self_entity c:icon on:press=run!(|ctx| {
+                ctx.commands().add(|world: &mut World| {
+                    world.send_event(MapEvent::spawn(Saw {
+                        position: IVec2::default(),
+                        path: SawPath::default(),
+                        speed: 2.,
+                        mode: SawMode::PingPong,
+                    }));
+                });
+            })>
+            <img src="Traps/Saw/Off.png"/>
+            </button>
+        }
+    }
+    fn draw_props(root: Entity) -> belly::core::eml::Eml {
+        eml!(<saw root=root/>)
+    }
+}
+
+#[widget]
+fn saw(ctx: &mut belly::build::WidgetContext) {
+    let Some(root) = ctx.required_param::<Entity>("root") else { return };
+    ctx.render(eml! {
+        <div id="editor">
+        <label value="Saw"/>
+        <button on:press=run!(for root |data: &mut Saw| {
+            data.speed = (data.speed - 0.5).max(0.5);
+        })>"-"</button>
+        <label bind:value=from!(root, Saw:speed|fmt.c("speed {}", c))/>
+        <button on:press=run!(for root |data: &mut Saw| {
+            data.speed += 0.5;
+        })>"+"</button>
+        <button on:press=run!(for root |data: &mut Saw| {
+            data.mode = data.mode.toggle();
+        })>"mode"</button>
+        <label bind:value=from!(root, Saw:mode|fmt.c("{:?}", c))/>
+        <button on:press=run!(for root |data: &mut Saw| {
+            data.path = match data.path {
+                SawPath::Circle { .. } => SawPath::default(),
+                SawPath::Waypoints(_) => SawPath::Circle { radius: 2. },
+            };
+        })>"path"</button>
+        <button on:press=run!(for root |data: &mut Saw| {
+            if let SawPath::Circle { radius } = &mut data.path {
+                *radius = (*radius - 1.).max(1.);
+            }
+        })>"-"</button>
+        <label bind:value=from!(root, Saw:path|fmt.c("{:?}", c))/>
+        <button on:press=run!(for root |data: &mut Saw| {
+            if let SawPath::Circle { radius } = &mut data.path {
+                *radius += 1.;
+            }
+        })>"+"</button>
+        </div>
+    });
+}
+
+// saws are placed from the ticks into the attempt, so every attempt sees them in the same place
+pub fn move_saws(
+    mut saws: Query<(&mut Transform, &Saw)>,
+    clock: Res<PlayClock>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    let seconds = clock.attempt_ticks() as f32 * tick_seconds(&rapier_config);
+    for (mut transform, saw) in &mut saws {
+        let pos = (saw.position * 16).as_vec2() + saw.offset(seconds);
+        transform.translation = pos.extend(transform.translation.z);
+    }
+}
+
+#[test]
+fn saws_follow_their_path() {
+    let mut saw = Saw {
+        position: IVec2::ZERO,
+        path: SawPath::Waypoints(vec![IVec2::new(2, 0), IVec2::new(2, 2)]),
+        speed: 1.,
+        mode: SawMode::PingPong,
+    };
+    assert_eq!(saw.offset(0.), Vec2::ZERO);
+    assert_eq!(saw.offset(1.), Vec2::new(16., 0.));
+    assert_eq!(saw.offset(3.), Vec2::new(32., 16.));
+    // turns round at the end and comes back
+    assert_eq!(saw.offset(5.), Vec2::new(32., 16.));
+    assert_eq!(saw.offset(8.), Vec2::ZERO);
+
+    // a loop heads straight back to the start instead
+    saw.mode = SawMode::Loop;
+    let back = 4. + 8f32.sqrt();
+    assert!(saw.offset(back / 2. + 2.).distance(Vec2::new(16., 16.)) < 0.001);
+    assert!(saw.offset(back).length() < 0.001);
+
+    saw.path = SawPath::Circle { radius: 1. };
+    assert_eq!(saw.offset(0.), Vec2::new(16., 0.));
+    let quarter = std::f32::consts::FRAC_PI_2;
+    assert!(saw.offset(quarter).distance(Vec2::new(0., 16.)) < 0.001);
+}
@@ -0,0 +1,214 @@
+use super::*;
+use crate::animation::{Animation, Animations};
+use crate::interaction::PlayerInteraction;
+use belly::{build::widget, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// which way the points face
+#[derive(Clone, Copy, Deserialize, Serialize, Reflect, Default, Debug, PartialEq, Eq)]
+pub enum SpikeDirection {
+    #[default]
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl SpikeDirection {
+    fn normal(self) -> Vec2 {
+        match self {
+            SpikeDirection::Up => Vec2::Y,
+            SpikeDirection::Left => Vec2::NEG_X,
+            SpikeDirection::Down => Vec2::NEG_Y,
+            SpikeDirection::Right => Vec2::X,
+        }
+    }
+
+    // the sprite points up, so this turns it to face the right way
+    fn angle(self) -> f32 {
+        match self {
+            SpikeDirection::Up => 0.,
+            SpikeDirection::Left => std::f32::consts::FRAC_PI_2,
+            SpikeDirection::Down => std::f32::consts::PI,
+            SpikeDirection::Right => -std::f32::consts::FRAC_PI_2,
+        }
+    }
+
+    // runs go right for spikes facing up or down, and up for the others
+    fn run_axis(self) -> IVec2 {
+        match self {
+            SpikeDirection::Up | SpikeDirection::Down => IVec2::X,
+            SpikeDirection::Left | SpikeDirection::Right => IVec2::Y,
+        }
+    }
+
+    pub fn rotate(self) -> SpikeDirection {
+        match self {
+            SpikeDirection::Up => SpikeDirection::Left,
+            SpikeDirection::Left => SpikeDirection::Down,
+            SpikeDirection::Down => SpikeDirection::Right,
+            SpikeDirection::Right => SpikeDirection::Up,
+        }
+    }
+}
+
+// a row of spikes starting at position, kills the player on touch
+#[derive(Component, Clone, Copy, Deserialize, Serialize, Reflect, Default)]
+pub struct Spikes {
+    pub position: IVec2,
+    pub length: i32,
+    pub direction: SpikeDirection,
+}
+
+impl Spikes {
+    fn length(&self) -> i32 {
+        self.length.max(1)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.length()).map(|i| self.position + self.direction.run_axis() * i)
+    }
+
+    // the middle of the run, pulled back onto the base of the spikes
+    fn transform(&self) -> Transform {
+        let run = (self.direction.run_axis() * (self.length() - 1)).as_vec2() * 8.;
+        let pos = (self.position * 16).as_vec2() + run - self.direction.normal() * 4.;
+        Transform::from_translation(pos.extend(1.))
+            .with_rotation(Quat::from_rotation_z(self.direction.angle()))
+    }
+}
+
+impl MapObject for Spikes {
+    fn spawn(
+        &self,
+        _terrain: &Animations,
+        commands: &mut Commands,
+        map_data: &mut MapData,
+    ) -> Option<Entity> {
+        self.set_full(map_data);
+        // sprites and collider are built by update_spikes
+        Some(
+            commands
+                .spawn((
+                    CellBundle {
+                        transform: self.transform(),
+                        rigid_body: RigidBody::Fixed,
+                        item: *self,
+                        ..Default::default()
+                    },
+                    Sensor,
+                    PlayerInteraction::Hurt,
+                    Name::new("Spikes"),
+                ))
+                .id(),
+        )
+    }
+    fn object_type(&self) -> super::levels::MapObjectType {
+        super::levels::MapObjectType::Spikes
+    }
+    fn serialize(&self) -> bevy::reflect::serde::Serializable {
+        bevy::reflect::serde::Serializable::Borrowed(self)
+    }
+    fn clone(&self) -> Box<dyn MapObject> {
+        Box::new(<Self as Clone>::clone(self))
+    }
+    fn set_full(&self, map: &mut MapData) {
+        for cell in self.cells() {
+            map.set_full(cell);
+        }
+    }
+}
+
+impl DrawProps for Spikes {
+    fn ui_draw(self_entity: Entity) -> belly::core::eml::Eml {
+        eml! {
+            <button entity=self_entity c:icon on:press=run!(|ctx| {
+                ctx.commands().add(|world: &mut World| {
+                    world.send_event(MapEvent::spawn(Spikes {
+                        position: IVec2::default(),
+                        length: 1,
+                        direction: SpikeDirection::Up,
+                    }));
+                });
+            })>
+            <img src="Traps/Spikes/Idle.png"/>
+            </button>
+        }
+    }
+    fn draw_props(root: Entity) -> belly::core::eml::Eml {
+        eml!(<spikes root=root/>)
+    }
+}
+
+#[widget]
+fn spikes(ctx: &mut belly::build::WidgetContext) {
+    let Some(root) = ctx.required_param::<Entity>("root") else { return };
+    ctx.render(eml! {
+        <div id="editor">
+        <label value="Spikes"/>
+        <button on:press=run!(for root |data: &mut Spikes| {
+            data.length = (data.length - 1).max(1);
+        })>"-"</button>
+        <label bind:value=from!(root, Spikes:length|fmt.c("length {}", c))/>
+        <button on:press=run!(for root |data: &mut Spikes| {
+            data.length += 1;
+        })>"+"</button>
+        <button on:press=run!(for root |data: &mut Spikes| {
+            data.direction = data.direction.rotate();
+        })>"rotate"</button>
+        <label bind:value=from!(root, Spikes:direction|fmt.c("{:?}", c))/>
+        </div>
+    });
+}
+
+pub fn update_spikes(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &Spikes), Changed<Spikes>>,
+    animations: Res<Animations>,
+) {
+    let Some(animation) = animations.get_animation(Animation::Spikes) else {error!("Animation for Spikes not loaded"); return;};
+    for (entity, mut transform, spikes) in &mut query {
+        *transform = spikes.transform();
+        let length = spikes.length();
+        // only the points hurt, the bottom half of the sprite is empty
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .insert(Collider::cuboid(length as f32 * 8. - 1., 3.))
+            .with_children(|p| {
+                for i in 0..length {
+                    let x = (i as f32 - (length - 1) as f32 / 2.) * 16.;
+                    p.spawn((
+                        SpatialBundle::from_transform(Transform::from_xyz(x, 4., 0.)),
+                        Handle::<TextureAtlas>::default(),
+                        TextureAtlasSprite::default(),
+                        animation.clone(),
+                    ));
+                }
+            });
+    }
+}
+
+#[test]
+fn spikes_cover_their_run() {
+    let spikes = Spikes {
+        position: IVec2::new(2, 3),
+        length: 3,
+        direction: SpikeDirection::Left,
+    };
+    let cells: Vec<IVec2> = spikes.cells().collect();
+    assert_eq!(cells, vec![IVec2::new(2, 3), IVec2::new(2, 4), IVec2::new(2, 5)]);
+    // centred on the middle cell, half way between base and tips
+    let transform = spikes.transform();
+    assert_eq!(transform.translation, Vec3::new(36., 64., 1.));
+    // the tips reach the middle of the cell
+    let tip = transform.transform_point(Vec3::Y * 4.);
+    assert!((tip.x - 32.).abs() < 0.001);
+
+    let flat = Spikes {
+        length: 0,
+        ..spikes
+    };
+    assert_eq!(flat.cells().count(), 1);
+}
@@ -2,6 +2,7 @@ use std::collections::HashSet;
 
 use crate::{animation::Animations, GameState};
 use bevy::{prelude::*, reflect::DynamicTypePath};
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use bevy_inspector_egui::prelude::*;
 
@@ -88,13 +89,40 @@ pub(crate) fn spawn_map_objects(
     }
 }
 
-#[derive(Default, Resource)]
+#[derive(Resource)]
 pub struct MapData {
     pub(super) need_correcting: bool,
     full: HashSet<IVec2>,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Default for MapData {
+    fn default() -> Self {
+        let seed = rand::random();
+        MapData {
+            need_correcting: false,
+            full: HashSet::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
 }
 
 impl MapData {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
     pub(crate) fn clear(&mut self) {
         self.full.clear();
     }
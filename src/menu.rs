@@ -1,16 +1,47 @@
 use crate::{
-    map::{Level, LoadedLevel},
+    achievement::{AchievementList, AchievementProgress, Achievements},
+    audio::{Cue, PlayCue, SoundCategory},
+    ghost::{GhostPreset, TimeTrial},
+    leaderboard::{format_date, GameMode, Leaderboard, LeaderboardView, RunResult},
+    level_complete::{load_next_level, LevelSummary},
+    map::{CurrentPack, Level, LevelHash, LevelPack, LoadedLevel},
+    pause,
+    profile::{switch_profile, Profile, ProfileList},
+    score::LastResult,
+    settings::GameSettings,
+    speedrun::tick_seconds,
+    transition::StartWipe,
+    user_input::MenuInput,
     GameState,
 };
 use belly::{core::input::Focused, prelude::*};
-use bevy::prelude::*;
+use bevy::{prelude::*, window::WindowMode};
+use bevy_rapier2d::prelude::RapierConfiguration;
+use leafwing_input_manager::prelude::*;
 
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MenuFont>()
+            .init_resource::<MenuCursor>()
             .add_systems(Startup, setup_belly)
+            .add_systems(Update, press_menu_buttons)
+            .add_systems(
+                Update,
+                navigate_menu.run_if(
+                    in_state(GameState::Menu)
+                        .or_else(in_state(GameState::InputLevelBase64))
+                        .or_else(in_state(GameState::InputLevelName))
+                        .or_else(in_state(GameState::Results))
+                        .or_else(in_state(GameState::Leaderboard))
+                        .or_else(in_state(GameState::Achievements))
+                        .or_else(in_state(GameState::Profiles))
+                        .or_else(in_state(GameState::Settings))
+                        .or_else(in_state(GameState::Paused))
+                        .or_else(in_state(GameState::LevelComplete)),
+                ),
+            )
             .add_systems(
                 StateTransition,
                 cleanup_old
@@ -20,6 +51,15 @@ impl Plugin for MenuPlugin {
             .add_systems(OnEnter(GameState::Menu), setup_main_menu)
             .add_systems(OnEnter(GameState::InputLevelBase64), setup_level_select)
             .add_systems(OnEnter(GameState::InputLevelName), setup_level_select)
+            .add_systems(OnEnter(GameState::Results), setup_results)
+            .add_systems(OnEnter(GameState::Leaderboard), setup_leaderboard)
+            .add_systems(OnEnter(GameState::Achievements), setup_achievements)
+            .add_systems(OnEnter(GameState::Profiles), setup_profiles)
+            .add_systems(OnEnter(GameState::Settings), setup_settings)
+            .init_resource::<PauseView>()
+            .add_systems(OnEnter(GameState::Paused), setup_pause)
+            .add_systems(OnEnter(GameState::LevelComplete), setup_level_complete)
+            .add_systems(OnExit(GameState::Paused), reset_pause_view)
             .add_systems(
                 OnTransition {
                     from: GameState::InputLevelBase64,
@@ -57,49 +97,861 @@ impl FromWorld for MenuFont {
     }
 }
 
-fn cleanup_old(mut elements: Elements, mut focus: ResMut<Focused>) {
+fn cleanup_old(mut elements: Elements, mut focus: ResMut<Focused>, mut cursor: ResMut<MenuCursor>) {
     focus.0 = None;
+    *cursor = MenuCursor::default();
     elements.select(".menu").remove()
 }
 
+// redraws the menu that is up in place, for buttons that only change what it shows,
+// setting the state it is already in would not run its OnEnter again
+fn rebuild<M>(world: &mut World, setup: impl IntoSystem<(), (), M>) {
+    let index = world.resource::<MenuCursor>().index;
+    run_once(world, cleanup_old);
+    run_once(world, setup);
+    // keep the keyboard/gamepad cursor on the button that was pressed
+    world.resource_mut::<MenuCursor>().index = index;
+}
+
+fn run_once<M>(world: &mut World, system: impl IntoSystem<(), (), M>) {
+    let mut system = IntoSystem::into_system(system);
+    system.initialize(world);
+    system.run((), world);
+    system.apply_deferred(world);
+}
+
+// run by a button press or by confirming it with the keyboard/gamepad
+type MenuAction = fn(&mut World);
+
+// plays the click sound then runs the action
+fn click(action: MenuAction) -> impl FnOnce(&mut World) + Send + 'static {
+    move |world: &mut World| {
+        world.send_event(PlayCue(Cue::MenuClick));
+        action(world);
+    }
+}
+
+// a .menu button, pressing it or confirming it with the cursor runs its action
+#[derive(Component)]
+struct MenuButton {
+    action: MenuAction,
+    pressed: bool,
+}
+
+fn menu_button(commands: &mut Commands, action: MenuAction) -> Entity {
+    commands
+        .spawn(MenuButton {
+            action,
+            pressed: false,
+        })
+        .id()
+}
+
+fn press_menu_buttons(
+    mut commands: Commands,
+    mut buttons: Query<&mut MenuButton, Changed<MenuButton>>,
+) {
+    for mut button in &mut buttons {
+        if button.pressed {
+            button.pressed = false;
+            commands.add(click(button.action));
+        }
+    }
+}
+
+// the buttons are read from the .menu in the order they are laid out
+#[derive(Resource, Default)]
+struct MenuCursor {
+    index: Option<usize>,
+    back: Option<MenuAction>,
+}
+
+impl MenuCursor {
+    fn new(back: Option<MenuAction>) -> MenuCursor {
+        MenuCursor { index: None, back }
+    }
+}
+
+fn navigate_menu(
+    mut commands: Commands,
+    input: Res<ActionState<MenuInput>>,
+    keys: Res<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut elements: Elements,
+    mut focus: ResMut<Focused>,
+    mut buttons: Query<&mut MenuButton>,
+    text_inputs: Query<(), With<TextInput>>,
+) {
+    if input.just_pressed(MenuInput::Back) {
+        // backspace is for the text while typing
+        let typing = keys.just_pressed(KeyCode::Back)
+            && focus.0.map_or(false, |entity| text_inputs.contains(entity));
+        if let (Some(back), false) = (cursor.back, typing) {
+            commands.add(click(back));
+        }
+        return;
+    }
+    let menu: Vec<Entity> = elements
+        .select(".menu button")
+        .entities()
+        .into_iter()
+        .filter(|entity| buttons.contains(*entity))
+        .collect();
+    if menu.is_empty() {
+        return;
+    }
+    let len = menu.len();
+    if input.just_pressed(MenuInput::Confirm) {
+        if let Some(index) = cursor.index {
+            if let Ok(mut button) = buttons.get_mut(menu[index.min(len - 1)]) {
+                button.pressed = true;
+            }
+        }
+        return;
+    }
+    // a rebuilt menu has new buttons, put the focus back where the cursor was
+    if let (Some(index), None) = (cursor.index, focus.0) {
+        focus.0 = Some(menu[index.min(len - 1)]);
+    }
+    let index = if input.just_pressed(MenuInput::Down) {
+        cursor.index.map(|i| (i + 1) % len).unwrap_or(0)
+    } else if input.just_pressed(MenuInput::Up) {
+        cursor.index.map(|i| (i + len - 1) % len).unwrap_or(len - 1)
+    } else {
+        return;
+    };
+    cursor.index = Some(index);
+    focus.0 = Some(menu[index]);
+}
+
+fn play(world: &mut World) {
+    world.resource_mut::<TimeTrial>().enabled = false;
+    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
+}
+
+// a pack level typed in by name has to be unlocked by the profile first
+fn play_level(world: &mut World) {
+    if *world.resource::<State<GameState>>().get() == GameState::InputLevelName {
+        let path = world
+            .query::<&TextInput>()
+            .iter(world)
+            .next()
+            .map(|input| input.value.clone())
+            .unwrap_or_default();
+        let pack = world
+            .resource::<Assets<LevelPack>>()
+            .get(&world.resource::<CurrentPack>().pack);
+        if pack.map_or(false, |pack| !world.resource::<Profile>().has_unlocked(pack, &path)) {
+            warn!("{} is locked, clear the level before it first", path);
+            return;
+        }
+    }
+    play(world);
+}
+
+fn time_trial(world: &mut World) {
+    world.resource_mut::<TimeTrial>().enabled = true;
+    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
+}
+
+fn input_base64(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::InputLevelBase64);
+}
+
+fn input_name(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::InputLevelName);
+}
+
+fn level_editor(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::LevelEditor);
+}
+
+fn leaderboard(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Leaderboard);
+}
+
+fn leaderboard_score(world: &mut World) {
+    world.resource_mut::<LeaderboardView>().0 = GameMode::Score;
+    rebuild(world, setup_leaderboard);
+}
+
+fn leaderboard_time(world: &mut World) {
+    world.resource_mut::<LeaderboardView>().0 = GameMode::TimeTrial;
+    rebuild(world, setup_leaderboard);
+}
+
+fn achievements(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Achievements);
+}
+
+fn profiles(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Profiles);
+}
+
+fn profile_previous(world: &mut World) {
+    world.resource_mut::<ProfileList>().previous();
+    rebuild(world, setup_profiles);
+}
+
+fn profile_next(world: &mut World) {
+    world.resource_mut::<ProfileList>().next();
+    rebuild(world, setup_profiles);
+}
+
+fn select_profile(world: &mut World) {
+    let list = world.resource::<ProfileList>();
+    let Some(name) = list.names.get(list.selected).cloned() else {return;};
+    match Profile::load(&list.dir, &name) {
+        Ok(profile) => {
+            switch_profile(world, profile);
+            main_menu(world);
+        }
+        Err(e) => error!("Failed to load profile {}: {}", name, e),
+    }
+}
+
+fn new_profile(world: &mut World) {
+    let name = world
+        .query::<&TextInput>()
+        .iter(world)
+        .next()
+        .map(|input| input.value.clone())
+        .unwrap_or_default();
+    let profile = Profile::new(&name);
+    if profile.name.is_empty() {
+        warn!("Profile needs a name");
+        return;
+    }
+    if world.resource::<ProfileList>().names.contains(&profile.name) {
+        warn!("Profile {} already exists", profile.name);
+        return;
+    }
+    switch_profile(world, profile);
+    main_menu(world);
+}
+
+fn settings(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Settings);
+}
+
+fn settings_window(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_window_mode();
+    rebuild(world, setup_settings);
+}
+
+fn settings_scale(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_scale();
+    rebuild(world, setup_settings);
+}
+
+fn settings_debug(world: &mut World) {
+    let mut game = world.resource_mut::<GameSettings>();
+    game.debug_physics = !game.debug_physics;
+    rebuild(world, setup_settings);
+}
+
+fn settings_easy(world: &mut World) {
+    preset_easy(world);
+    rebuild(world, setup_settings);
+}
+
+fn settings_normal(world: &mut World) {
+    preset_normal(world);
+    rebuild(world, setup_settings);
+}
+
+fn settings_hard(world: &mut World) {
+    preset_hard(world);
+    rebuild(world, setup_settings);
+}
+
+fn settings_gravity(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_gravity_scale();
+    rebuild(world, setup_settings);
+}
+
+fn settings_harmless(world: &mut World) {
+    let mut game = world.resource_mut::<GameSettings>();
+    game.harmless_ghosts = !game.harmless_ghosts;
+    rebuild(world, setup_settings);
+}
+
+fn settings_volume(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_volume(None);
+    rebuild(world, setup_settings);
+}
+
+fn settings_music(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_volume(Some(SoundCategory::Music));
+    rebuild(world, setup_settings);
+}
+
+fn settings_effects(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_volume(Some(SoundCategory::Effects));
+    rebuild(world, setup_settings);
+}
+
+fn settings_ui(world: &mut World) {
+    world.resource_mut::<GameSettings>().next_volume(Some(SoundCategory::Ui));
+    rebuild(world, setup_settings);
+}
+
+fn resume(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
+}
+
+fn restart(world: &mut World) {
+    pause::restart_level(world);
+    resume(world);
+}
+
+fn pause_settings(world: &mut World) {
+    *world.resource_mut::<PauseView>() = PauseView::Settings;
+    rebuild(world, setup_pause);
+}
+
+fn pause_main(world: &mut World) {
+    *world.resource_mut::<PauseView>() = PauseView::Main;
+    rebuild(world, setup_pause);
+}
+
+fn next_level(world: &mut World) {
+    world.send_event(StartWipe(load_next_level));
+}
+
+fn replay_level(world: &mut World) {
+    pause::restart_level(world);
+    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
+}
+
+fn retry(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
+}
+
+fn main_menu(world: &mut World) {
+    world.resource_mut::<NextState<GameState>>().set(GameState::Menu);
+}
+
+fn preset_easy(world: &mut World) {
+    *world.resource_mut::<GhostPreset>() = GhostPreset::Easy;
+}
+
+fn preset_normal(world: &mut World) {
+    *world.resource_mut::<GhostPreset>() = GhostPreset::Normal;
+}
+
+fn preset_hard(world: &mut World) {
+    *world.resource_mut::<GhostPreset>() = GhostPreset::Hard;
+}
+
 fn setup_main_menu(mut commands: Commands) {
+    commands.insert_resource(MenuCursor::new(None));
+    let play_button = menu_button(&mut commands, play);
+    let time_trial_button = menu_button(&mut commands, time_trial);
+    let input_base64_button = menu_button(&mut commands, input_base64);
+    let input_name_button = menu_button(&mut commands, input_name);
+    let level_editor_button = menu_button(&mut commands, level_editor);
+    let leaderboard_button = menu_button(&mut commands, leaderboard);
+    let achievements_button = menu_button(&mut commands, achievements);
+    let profiles_button = menu_button(&mut commands, profiles);
+    let settings_button = menu_button(&mut commands, settings);
+    let preset_easy_button = menu_button(&mut commands, preset_easy);
+    let preset_normal_button = menu_button(&mut commands, preset_normal);
+    let preset_hard_button = menu_button(&mut commands, preset_hard);
     commands.add(eml! {
         <div c:menu>
-            <button on:press=run!(|ctx| {
-                ctx.commands().add(move |world: &mut World| {
-                    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
-                    println!("{:?}", world.resource::<State<GameState>>());
-                });
+            <button entity=play_button on:press=run!(for play_button |button: &mut MenuButton| {
+                button.pressed = true;
             }) value="play"><label value="Play"/></button>
-            <button on:press=run!(|ctx| {
-                ctx.commands().add(move |world: &mut World| {
-                    world.resource_mut::<NextState<GameState>>().set(GameState::InputLevelBase64);
-                });
+            <button entity=time_trial_button on:press=run!(for time_trial_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="time_trial"><label value="Time Trial"/></button>
+            <button entity=input_base64_button on:press=run!(for input_base64_button |button: &mut MenuButton| {
+                button.pressed = true;
             }) value="base"><label value="Base64"/></button>
-            <button on:press=run!(|ctx| {
-                ctx.commands().add(move |world: &mut World| {
-                    world.resource_mut::<NextState<GameState>>().set(GameState::InputLevelName);
-                });
+            <button entity=input_name_button on:press=run!(for input_name_button |button: &mut MenuButton| {
+                button.pressed = true;
             }) value="name"><label value="Name"/></button>
-            <button on:press=run!(|ctx| {
-                ctx.commands().add(move |world: &mut World| {
-                    world.resource_mut::<NextState<GameState>>().set(GameState::LevelEditor);
-                });
+            <button entity=level_editor_button on:press=run!(for level_editor_button |button: &mut MenuButton| {
+                button.pressed = true;
             }) value="editor"><label value="Level Editor"/></button>
+            <button entity=leaderboard_button on:press=run!(for leaderboard_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="leaderboard">
+                <img src="Menu/Buttons/Leaderboard.png"/>
+                <label value="Leaderboard"/>
+            </button>
+            <button entity=achievements_button on:press=run!(for achievements_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="achievements">
+                <img src="Menu/Buttons/Achievements.png"/>
+                <label value="Achievements"/>
+            </button>
+            <button entity=profiles_button on:press=run!(for profiles_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="profiles"><label value="Profiles"/></button>
+            <button entity=settings_button on:press=run!(for settings_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="settings">
+                <img src="Menu/Buttons/Settings.png"/>
+                <label value="Settings"/>
+            </button>
+            <div c:difficulty>
+                <button entity=preset_easy_button on:press=run!(for preset_easy_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="easy"><label value="Easy"/></button>
+                <button entity=preset_normal_button on:press=run!(for preset_normal_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="normal"><label value="Normal"/></button>
+                <button entity=preset_hard_button on:press=run!(for preset_hard_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="hard"><label value="Hard"/></button>
+            </div>
         </div>
     });
 }
 
 fn setup_level_select(mut commands: Commands) {
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let play_button = menu_button(&mut commands, play_level);
+    commands.add(eml! {
+        <div c:menu>
+            <textinput />
+            <button entity=play_button on:press=run!(for play_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="play"><label value="Play"/></button>
+        </div>
+    });
+}
+
+fn setup_results(mut commands: Commands, result: Res<LastResult>) {
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let score = format!("Score: {}", result.score);
+    let best = if result.assisted {
+        format!("Best: {} (gravity assist, not saved)", result.best)
+    } else if result.new_best {
+        format!("New Best: {}", result.best)
+    } else {
+        format!("Best: {}", result.best)
+    };
+    let retry_button = menu_button(&mut commands, retry);
+    let main_menu_button = menu_button(&mut commands, main_menu);
+    commands.add(eml! {
+        <div c:menu>
+            <label value=score/>
+            <label value=best/>
+            <button entity=retry_button on:press=run!(for retry_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="retry"><label value="Retry"/></button>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="menu"><label value="Main Menu"/></button>
+        </div>
+    });
+}
+
+fn setup_leaderboard(
+    mut commands: Commands,
+    leaderboard: Res<Leaderboard>,
+    view: Res<LeaderboardView>,
+    level_hash: Res<LevelHash>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let dt = tick_seconds(&rapier_config);
+    let title = match view.0 {
+        GameMode::Score => "Leaderboard: Score",
+        GameMode::TimeTrial => "Leaderboard: Time Trial",
+    };
+    let mut lines = Vec::new();
+    match level_hash.0 {
+        Some(hash) => {
+            for (rank, entry) in leaderboard.entries(hash, view.0).enumerate() {
+                let result = match entry.result {
+                    RunResult::Score(score) => format!("{}", score),
+                    RunResult::Time(frames) => format!("{:.2}s", frames as f32 * dt),
+                };
+                lines.push(format!(
+                    "{}. {} {} {:?} {}",
+                    rank + 1,
+                    entry.name,
+                    result,
+                    entry.character,
+                    format_date(entry.date)
+                ));
+            }
+            if lines.is_empty() {
+                lines.push("No runs yet".to_string());
+            }
+        }
+        None => lines.push("Play a level to see its leaderboard".to_string()),
+    }
+    let leaderboard_score_button = menu_button(&mut commands, leaderboard_score);
+    let leaderboard_time_button = menu_button(&mut commands, leaderboard_time);
+    let main_menu_button = menu_button(&mut commands, main_menu);
+    commands.add(eml! {
+        <div c:menu>
+            <label value=title/>
+            <div c:leaderboard>
+                <for line in=lines>
+                    <label value=line/>
+                </for>
+            </div>
+            <div c:difficulty>
+                <button entity=leaderboard_score_button on:press=run!(for leaderboard_score_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="score"><label value="Score"/></button>
+                <button entity=leaderboard_time_button on:press=run!(for leaderboard_time_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="time"><label value="Time Trial"/></button>
+            </div>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="back">
+                <img src="Menu/Buttons/Back.png"/>
+                <label value="Back"/>
+            </button>
+        </div>
+    });
+}
+
+fn setup_level_complete(mut commands: Commands, summary: Res<LevelSummary>) {
+    let time = if summary.assisted {
+        format!("Time: {:.2}s (gravity assist)", summary.time)
+    } else {
+        format!("Time: {:.2}s", summary.time)
+    };
+    let score = format!("Score: {}", summary.score);
+    let deaths = format!("Deaths: {}", summary.deaths);
+    if summary.has_next {
+        commands.insert_resource(MenuCursor::new(Some(main_menu)));
+        let next_level_button = menu_button(&mut commands, next_level);
+        let replay_level_button = menu_button(&mut commands, replay_level);
+        let main_menu_button = menu_button(&mut commands, main_menu);
+        commands.add(eml! {
+            <div c:menu>
+                <label value="Level Complete"/>
+                <label value=time/>
+                <label value=score/>
+                <label value=deaths/>
+                <button entity=next_level_button on:press=run!(for next_level_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="next">
+                    <img src="Menu/Buttons/Next.png"/>
+                    <label value="Next Level"/>
+                </button>
+                <button entity=replay_level_button on:press=run!(for replay_level_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="retry">
+                    <img src="Menu/Buttons/Restart.png"/>
+                    <label value="Retry"/>
+                </button>
+                <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="menu">
+                    <img src="Menu/Buttons/Back.png"/>
+                    <label value="Main Menu"/>
+                </button>
+            </div>
+        });
+        return;
+    }
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let replay_level_button = menu_button(&mut commands, replay_level);
+    let main_menu_button = menu_button(&mut commands, main_menu);
+    commands.add(eml! {
+        <div c:menu>
+            <label value="Level Complete"/>
+            <label value=time/>
+            <label value=score/>
+            <label value=deaths/>
+            <button entity=replay_level_button on:press=run!(for replay_level_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="retry">
+                <img src="Menu/Buttons/Restart.png"/>
+                <label value="Retry"/>
+            </button>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="menu">
+                <img src="Menu/Buttons/Back.png"/>
+                <label value="Main Menu"/>
+            </button>
+        </div>
+    });
+}
+
+// which page of the pause overlay is up
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+enum PauseView {
+    #[default]
+    Main,
+    Settings,
+}
+
+fn reset_pause_view(mut view: ResMut<PauseView>) {
+    *view = PauseView::Main;
+}
+
+fn setup_pause(mut commands: Commands, view: Res<PauseView>) {
+    if *view == PauseView::Settings {
+        commands.insert_resource(MenuCursor::new(Some(pause_main)));
+        let preset_easy_button = menu_button(&mut commands, preset_easy);
+        let preset_normal_button = menu_button(&mut commands, preset_normal);
+        let preset_hard_button = menu_button(&mut commands, preset_hard);
+        let pause_main_button = menu_button(&mut commands, pause_main);
+        commands.add(eml! {
+            <div c:menu>
+                <label value="Settings"/>
+                <div c:difficulty>
+                    <button entity=preset_easy_button on:press=run!(for preset_easy_button |button: &mut MenuButton| {
+                        button.pressed = true;
+                    }) value="easy"><label value="Easy"/></button>
+                    <button entity=preset_normal_button on:press=run!(for preset_normal_button |button: &mut MenuButton| {
+                        button.pressed = true;
+                    }) value="normal"><label value="Normal"/></button>
+                    <button entity=preset_hard_button on:press=run!(for preset_hard_button |button: &mut MenuButton| {
+                        button.pressed = true;
+                    }) value="hard"><label value="Hard"/></button>
+                </div>
+                <button entity=pause_main_button on:press=run!(for pause_main_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="back">
+                    <img src="Menu/Buttons/Back.png"/>
+                    <label value="Back"/>
+                </button>
+            </div>
+        });
+        return;
+    }
+    commands.insert_resource(MenuCursor::new(Some(resume)));
+    let resume_button = menu_button(&mut commands, resume);
+    let restart_button = menu_button(&mut commands, restart);
+    let pause_settings_button = menu_button(&mut commands, pause_settings);
+    let main_menu_button = menu_button(&mut commands, main_menu);
+    commands.add(eml! {
+        <div c:menu>
+            <label value="Paused"/>
+            <button entity=resume_button on:press=run!(for resume_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="resume">
+                <img src="Menu/Buttons/Close.png"/>
+                <label value="Resume"/>
+            </button>
+            <button entity=restart_button on:press=run!(for restart_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="restart">
+                <img src="Menu/Buttons/Restart.png"/>
+                <label value="Restart"/>
+            </button>
+            <button entity=pause_settings_button on:press=run!(for pause_settings_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="settings">
+                <img src="Menu/Buttons/Settings.png"/>
+                <label value="Settings"/>
+            </button>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="quit">
+                <img src="Menu/Buttons/Back.png"/>
+                <label value="Quit to Menu"/>
+            </button>
+        </div>
+    });
+}
+
+fn setup_achievements(
+    mut commands: Commands,
+    achievements: Res<Achievements>,
+    lists: Res<Assets<AchievementList>>,
+    progress: Res<AchievementProgress>,
+) {
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let mut lines = Vec::new();
+    match lists.get(&achievements.0) {
+        Some(list) => {
+            for def in list.0.iter() {
+                let status = if progress.is_unlocked(&def.id) {
+                    "Unlocked".to_string()
+                } else {
+                    format!("{}/{}", progress.get(def.stat).min(def.goal), def.goal)
+                };
+                lines.push(format!("{} - {} ({})", def.name, def.description, status));
+            }
+        }
+        None => lines.push("Achievements still loading".to_string()),
+    }
+    let main_menu_button = menu_button(&mut commands, main_menu);
     commands.add(eml! {
         <div c:menu>
+            <label value="Achievements"/>
+            <div c:leaderboard>
+                <for line in=lines>
+                    <label value=line/>
+                </for>
+            </div>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="back">
+                <img src="Menu/Buttons/Back.png"/>
+                <label value="Back"/>
+            </button>
+        </div>
+    });
+}
+
+fn setup_profiles(mut commands: Commands, list: Res<ProfileList>, profile: Res<Profile>) {
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let title = format!("Playing as {}", profile.name);
+    let unlocked = format!("Levels unlocked: {}", profile.unlocked.len());
+    let lines: Vec<String> = list
+        .names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            if i == list.selected {
+                format!("> {}", name)
+            } else {
+                name.clone()
+            }
+        })
+        .collect();
+    let profile_previous_button = menu_button(&mut commands, profile_previous);
+    let profile_next_button = menu_button(&mut commands, profile_next);
+    let select_profile_button = menu_button(&mut commands, select_profile);
+    let new_profile_button = menu_button(&mut commands, new_profile);
+    let main_menu_button = menu_button(&mut commands, main_menu);
+    commands.add(eml! {
+        <div c:menu>
+            <label value=title/>
+            <label value=unlocked/>
+            <div c:leaderboard>
+                <for line in=lines>
+                    <label value=line/>
+                </for>
+            </div>
+            <div c:difficulty>
+                <button entity=profile_previous_button on:press=run!(for profile_previous_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="previous"><img src="Menu/Buttons/Previous.png"/></button>
+                <button entity=profile_next_button on:press=run!(for profile_next_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="next"><img src="Menu/Buttons/Next.png"/></button>
+            </div>
+            <button entity=select_profile_button on:press=run!(for select_profile_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="select">
+                <img src="Menu/Buttons/Play.png"/>
+                <label value="Select"/>
+            </button>
             <textinput />
-            <button value="play" on:press=run!(|ctx| {
-                ctx.commands().add(move |world: &mut World| {
-                    world.resource_mut::<NextState<GameState>>().set(GameState::Play);
-                    println!("{:?}", world.resource::<State<GameState>>());
-                });
-            })><label value="Play"/></button>
+            <button entity=new_profile_button on:press=run!(for new_profile_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="new"><label value="New Profile"/></button>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="back">
+                <img src="Menu/Buttons/Back.png"/>
+                <label value="Back"/>
+            </button>
+        </div>
+    });
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn setup_settings(mut commands: Commands, game: Res<GameSettings>, preset: Res<GhostPreset>) {
+    commands.insert_resource(MenuCursor::new(Some(main_menu)));
+    let window = match game.window_mode {
+        WindowMode::Windowed => "Window: Windowed",
+        WindowMode::BorderlessFullscreen => "Window: Borderless",
+        _ => "Window: Fullscreen",
+    };
+    let scale = format!("Scale: {}x", game.scale);
+    let debug = format!("Physics Debug: {}", on_off(game.debug_physics));
+    let ghosts = format!("Ghosts: {:?}", *preset);
+    let gravity = format!(
+        "Gravity: {}%{}",
+        (game.gravity_scale * 100.).round(),
+        if game.gravity_assisted() { " (runs not saved)" } else { "" }
+    );
+    let harmless = format!("Harmless Ghosts: {}", on_off(game.harmless_ghosts));
+    let volume = format!("Volume: {}%", (game.volume * 100.).round());
+    let music = format!("Music: {}%", (game.music_volume * 100.).round());
+    let effects = format!("Effects: {}%", (game.effects_volume * 100.).round());
+    let ui = format!("Interface: {}%", (game.ui_volume * 100.).round());
+    let settings_window_button = menu_button(&mut commands, settings_window);
+    let settings_scale_button = menu_button(&mut commands, settings_scale);
+    let settings_debug_button = menu_button(&mut commands, settings_debug);
+    let settings_easy_button = menu_button(&mut commands, settings_easy);
+    let settings_normal_button = menu_button(&mut commands, settings_normal);
+    let settings_hard_button = menu_button(&mut commands, settings_hard);
+    let settings_gravity_button = menu_button(&mut commands, settings_gravity);
+    let settings_harmless_button = menu_button(&mut commands, settings_harmless);
+    let settings_volume_button = menu_button(&mut commands, settings_volume);
+    let settings_music_button = menu_button(&mut commands, settings_music);
+    let settings_effects_button = menu_button(&mut commands, settings_effects);
+    let settings_ui_button = menu_button(&mut commands, settings_ui);
+    let main_menu_button = menu_button(&mut commands, main_menu);
+    commands.add(eml! {
+        <div c:menu>
+            <label value="Settings"/>
+            <button entity=settings_window_button on:press=run!(for settings_window_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="window"><label value=window/></button>
+            <button entity=settings_scale_button on:press=run!(for settings_scale_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="scale"><label value=scale/></button>
+            <button entity=settings_debug_button on:press=run!(for settings_debug_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="debug"><label value=debug/></button>
+            <label value=ghosts/>
+            <div c:difficulty>
+                <button entity=settings_easy_button on:press=run!(for settings_easy_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="easy"><label value="Easy"/></button>
+                <button entity=settings_normal_button on:press=run!(for settings_normal_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="normal"><label value="Normal"/></button>
+                <button entity=settings_hard_button on:press=run!(for settings_hard_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="hard"><label value="Hard"/></button>
+            </div>
+            <button entity=settings_gravity_button on:press=run!(for settings_gravity_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="gravity"><label value=gravity/></button>
+            <button entity=settings_harmless_button on:press=run!(for settings_harmless_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="harmless"><label value=harmless/></button>
+            <button entity=settings_volume_button on:press=run!(for settings_volume_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="volume">
+                <img src="Menu/Buttons/Volume.png"/>
+                <label value=volume/>
+            </button>
+            <div c:difficulty>
+                <button entity=settings_music_button on:press=run!(for settings_music_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="music"><label value=music/></button>
+                <button entity=settings_effects_button on:press=run!(for settings_effects_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="effects"><label value=effects/></button>
+                <button entity=settings_ui_button on:press=run!(for settings_ui_button |button: &mut MenuButton| {
+                    button.pressed = true;
+                }) value="ui"><label value=ui/></button>
+            </div>
+            <button entity=main_menu_button on:press=run!(for main_menu_button |button: &mut MenuButton| {
+                button.pressed = true;
+            }) value="back">
+                <img src="Menu/Buttons/Back.png"/>
+                <label value="Back"/>
+            </button>
         </div>
     });
 }
@@ -108,6 +960,7 @@ fn load_base64_level(
     mut levels: ResMut<Assets<Level>>,
     mut elements: Elements,
     mut loaded_level: ResMut<LoadedLevel>,
+    mut current_pack: ResMut<CurrentPack>,
     query: Query<&TextInput>,
 ) {
     let data = *elements
@@ -119,6 +972,7 @@ fn load_base64_level(
     match Level::from_base64(&textinput.value) {
         Ok(level) => {
             loaded_level.0 = levels.add(level);
+            current_pack.index = None;
         }
         Err(e) => {
             error!("{}", e);
@@ -130,6 +984,8 @@ fn load_name_level(
     asset_server: Res<AssetServer>,
     mut elements: Elements,
     mut loaded_level: ResMut<LoadedLevel>,
+    mut current_pack: ResMut<CurrentPack>,
+    packs: Res<Assets<LevelPack>>,
     query: Query<&TextInput>,
 ) {
     let data = *elements
@@ -139,4 +995,5 @@ fn load_name_level(
         .expect("textinput in menu");
     let textinput = query.get(data).expect("textinput is not TextInput");
     loaded_level.0 = asset_server.load(&textinput.value);
+    current_pack.select(&packs, &textinput.value);
 }
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    ghost::{is_rewinding, GhostEvents},
+    map::LoadedLevel,
+    player::RealPlayer,
+    user_input::MenuInput,
+    GameState, Score,
+};
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Resumed>()
+            .add_systems(OnEnter(GameState::Paused), freeze_physics)
+            // a rewind keeps physics off until it finishes
+            .add_systems(
+                OnExit(GameState::Paused),
+                (resume_physics.run_if(not(is_rewinding)), mark_resumed),
+            )
+            .add_systems(
+                Update,
+                toggle_pause.run_if(in_state(GameState::Play).or_else(in_state(GameState::Paused))),
+            )
+            .add_systems(Update, clear_resumed.run_if(resumed));
+    }
+}
+
+// set for the frame play picks back up after the pause menu,
+// so OnEnter(Play) setup does not run again
+#[derive(Resource, Default)]
+pub struct Resumed(bool);
+
+pub fn resumed(resumed: Res<Resumed>) -> bool {
+    resumed.0
+}
+
+// true once play is over, rather than just paused or resumed
+pub fn leaving_play(state: Res<State<GameState>>) -> bool {
+    !matches!(state.get(), GameState::Play | GameState::Paused)
+}
+
+pub fn is_paused(state: Res<State<GameState>>) -> bool {
+    *state.get() == GameState::Paused
+}
+
+fn freeze_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn resume_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+// state has already moved on by the time OnExit runs
+fn mark_resumed(state: Res<State<GameState>>, mut resumed: ResMut<Resumed>) {
+    resumed.0 = *state.get() == GameState::Play;
+}
+
+fn clear_resumed(mut resumed: ResMut<Resumed>) {
+    resumed.0 = false;
+}
+
+fn toggle_pause(
+    input: Res<ActionState<MenuInput>>,
+    state: Res<State<GameState>>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    if !input.just_pressed(MenuInput::Pause) {
+        return;
+    }
+    // escape is back as well, the pause menu decides where that goes
+    if *state.get() == GameState::Paused && input.just_pressed(MenuInput::Back) {
+        return;
+    }
+    next.set(match state.get() {
+        GameState::Paused => GameState::Play,
+        _ => GameState::Paused,
+    });
+}
+
+// back to the start of the level with a clean slate
+pub fn restart_level(world: &mut World) {
+    let mut events = world.resource_mut::<Events<GhostEvents>>();
+    events.send(GhostEvents::ClearGhosts);
+    events.send(GhostEvents::ClearTrail);
+    world.resource_mut::<Score>().0 = 0;
+    world.resource_mut::<LoadedLevel>().set_changed();
+    let mut player = world.query_filtered::<(&mut Transform, &mut Velocity), With<RealPlayer>>();
+    for (mut pos, mut vel) in player.iter_mut(world) {
+        *vel = Velocity::zero();
+        *pos = Transform::IDENTITY;
+    }
+}
+
+#[test]
+fn restart_resets_player_and_score() {
+    let mut world = World::new();
+    world.init_resource::<Events<GhostEvents>>();
+    world.insert_resource(Score(4));
+    world.insert_resource(LoadedLevel(Handle::default()));
+    let player = world
+        .spawn((
+            RealPlayer,
+            Transform::from_xyz(40., 12., 0.),
+            Velocity::linear(Vec2::X * 30.),
+        ))
+        .id();
+    restart_level(&mut world);
+    assert_eq!(world.resource::<Score>().0, 0);
+    assert_eq!(world.get::<Transform>(player), Some(&Transform::IDENTITY));
+    assert_eq!(world.get::<Velocity>(player), Some(&Velocity::zero()));
+    assert_eq!(world.resource::<Events<GhostEvents>>().len(), 2);
+}
@@ -3,6 +3,7 @@ use crate::user_input::PlayerInput;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone)]
 pub enum PlayerStages {
@@ -13,7 +14,8 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
+        app.add_event::<PlayerMovement>()
+            .add_systems(Startup, spawn_player)
             .add_systems(Update, move_player.in_set(PlayerStages::Move))
             .add_systems(Update, ground_detection)
             .add_systems(
@@ -31,7 +33,7 @@ impl Plugin for PlayerPlugin {
     }
 }
 
-#[derive(Component, Reflect, PartialEq, Clone, Copy)]
+#[derive(Component, Reflect, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Player {
     Mask,
     Ninja,
@@ -42,6 +44,17 @@ pub enum Player {
 #[derive(Component)]
 pub struct RealPlayer;
 
+// things the real player did that other systems might want to react to
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerMovement {
+    Jump,
+    DoubleJump,
+    Land,
+}
+
+// kept out of GROUP_1 so ghosts only touch the player when they are lethal
+pub const PLAYER_GROUP: Group = Group::GROUP_3;
+
 fn spawn_player(mut commands: Commands, animations: Res<Animations>) {
     let Some(handle) = animations.get_animation(Animation::MaskIdle) else {error!("Failed to find animation: Idle"); return;};
     commands.spawn((
@@ -75,7 +88,12 @@ fn spawn_player(mut commands: Commands, animations: Res<Animations>) {
             linear_damping: 1.,
             angular_damping: 1.,
         },
-        Name::new("Player"),
+        (
+            Name::new("Player"),
+            CollisionGroups::new(PLAYER_GROUP, Group::ALL),
+            // interactions are driven by the player's collision events
+            ActiveEvents::COLLISION_EVENTS,
+        ),
     ));
 }
 
@@ -88,12 +106,17 @@ fn move_player(
         &ActionState<PlayerInput>,
         &Grounded,
         &Transform,
+        Option<&RealPlayer>,
     )>,
     rapier_context: Res<RapierContext>,
+    mut movement: EventWriter<PlayerMovement>,
 ) {
-    for (mut velocity, input, grounded, pos) in &mut player {
+    for (mut velocity, input, grounded, pos, real) in &mut player {
         if input.just_pressed(PlayerInput::Jump) & grounded {
             velocity.linvel.y = 250.;
+            if real.is_some() {
+                movement.send(PlayerMovement::Jump);
+            }
         } else if input.just_pressed(PlayerInput::Fall) {
             velocity.linvel.y = velocity.linvel.y.min(0.0);
         } else if input.pressed(PlayerInput::Left) {
@@ -124,23 +147,32 @@ fn move_player(
 }
 
 fn dubble_jump(
-    mut player: Query<(&mut Jump, &mut Velocity, &ActionState<PlayerInput>)>,
+    mut player: Query<(
+        &mut Jump,
+        &mut Velocity,
+        &ActionState<PlayerInput>,
+        Option<&RealPlayer>,
+    )>,
     can_jump: Query<(Entity, &Grounded), Changed<Grounded>>,
+    mut movement: EventWriter<PlayerMovement>,
 ) {
     for (entity, grounded) in &can_jump {
-        if let Ok((mut jump, _, _)) = player.get_mut(entity) {
+        if let Ok((mut jump, _, _, _)) = player.get_mut(entity) {
             if grounded.0 {
                 jump.0 = true;
             }
         }
     }
-    for (mut jump, mut velocity, input) in player.iter_mut() {
+    for (mut jump, mut velocity, input, real) in player.iter_mut() {
         if velocity.linvel.y.abs() < 0.01 {
             return;
         }
         if input.just_pressed(PlayerInput::Jump) && jump.0 {
             jump.0 = false;
             velocity.linvel.y = 250.;
+            if real.is_some() {
+                movement.send(PlayerMovement::DoubleJump);
+            }
         }
     }
 }
@@ -170,12 +202,23 @@ pub struct Jump(pub bool);
 
 #[derive(Component, Reflect)]
 pub struct Grounded(pub bool);
+// the height last frame and how many frames it has held still for
 #[derive(Component, Default)]
 pub struct GroundedCheck(f32, isize);
 
-fn ground_detection(mut player: Query<(&Transform, &mut Grounded, &mut GroundedCheck)>) {
-    for (pos, mut on_ground, mut last) in &mut player {
-        if (pos.translation.y * 100.).round() == last.0 {
+impl GroundedCheck {
+    // moved by a platform, so that doesn't count as leaving the ground
+    pub fn carry(&mut self, by: Vec2) {
+        self.0 += by.y;
+    }
+}
+
+fn ground_detection(
+    mut player: Query<(&Transform, &mut Grounded, &mut GroundedCheck, Option<&RealPlayer>)>,
+    mut movement: EventWriter<PlayerMovement>,
+) {
+    for (pos, mut on_ground, mut last, real) in &mut player {
+        if (pos.translation.y * 100.).round() == (last.0 * 100.).round() {
             last.1 += 1;
         } else {
             last.1 -= 1;
@@ -184,11 +227,14 @@ fn ground_detection(mut player: Query<(&Transform, &mut Grounded, &mut GroundedC
 
         if last.1 == 5 && !on_ground.0 {
             on_ground.0 = true;
+            if real.is_some() {
+                movement.send(PlayerMovement::Land);
+            }
         } else if last.1 < 2 && on_ground.0 {
             on_ground.0 = false;
         }
 
-        last.0 = (pos.translation.y * 100.).round();
+        last.0 = pos.translation.y;
     }
 }
 
@@ -268,3 +314,34 @@ fn auto_step(
         }
     }
 }
+
+#[test]
+fn riding_a_platform_stays_grounded() {
+    let mut app = App::new();
+    app.add_event::<PlayerMovement>()
+        .add_systems(Update, ground_detection);
+    let player = app
+        .world
+        .spawn((Transform::default(), Grounded(false), GroundedCheck::default()))
+        .id();
+    for _ in 0..5 {
+        app.update();
+    }
+    assert!(app.world.get::<Grounded>(player).unwrap().0);
+    // a platform going down takes the player with it
+    for _ in 0..10 {
+        app.world.get_mut::<Transform>(player).unwrap().translation.y -= 1.5;
+        app.world
+            .get_mut::<GroundedCheck>(player)
+            .unwrap()
+            .carry(Vec2::NEG_Y * 1.5);
+        app.update();
+    }
+    assert!(app.world.get::<Grounded>(player).unwrap().0);
+    // without the carry it is falling
+    for _ in 0..5 {
+        app.world.get_mut::<Transform>(player).unwrap().translation.y -= 1.5;
+        app.update();
+    }
+    assert!(!app.world.get::<Grounded>(player).unwrap().0);
+}
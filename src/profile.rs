@@ -0,0 +1,413 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievement::AchievementProgress,
+    ghost::GhostPreset,
+    leaderboard::PlayerName,
+    level_complete::LevelSummary,
+    map::{CurrentPack, LevelHash, LevelPack},
+    player::{Player, RealPlayer},
+    score::HighScores,
+    settings::GameSettings,
+    speedrun::SpeedrunSettings,
+    user_input::{InputSettings, KeyBindings},
+    GameState,
+};
+
+const PROFILE_VERSION: u8 = 0;
+const APP_DIR: &str = "bevy_platformer";
+// holds the name of the profile to load on start
+const ACTIVE_FILE: &str = "active.txt";
+// saves from before profiles, folded into the first profile made
+const LEGACY_HIGH_SCORES: &str = "saves/high_scores.ron";
+const LEGACY_ACHIEVEMENTS: &str = "saves/achievements.ron";
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ProfileList::scan(profile_dir()))
+            .add_event::<ProfileLoaded>()
+            .add_systems(PreStartup, load_active_profile)
+            .add_systems(Update, apply_character)
+            .add_systems(OnEnter(GameState::LevelComplete), record_clear)
+            .add_systems(Last, sync_profile);
+    }
+}
+
+// the platform's per user data folder
+fn user_data_dir() -> Option<PathBuf> {
+    let var = |key: &str| {
+        std::env::var_os(key)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local/share")))
+    }
+}
+
+pub fn profile_dir() -> PathBuf {
+    match user_data_dir() {
+        Some(dir) => dir.join(APP_DIR).join("profiles"),
+        None => PathBuf::from("saves/profiles"),
+    }
+}
+
+// profile names double as file names
+fn clean_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | ' ' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    pub ghost_preset: GhostPreset,
+    pub deadzone: f32,
+    pub show_timer: bool,
+    #[serde(default)]
+    pub game: GameSettings,
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        ProfileSettings {
+            ghost_preset: GhostPreset::default(),
+            deadzone: InputSettings::default().deadzone,
+            show_timer: SpeedrunSettings::default().show_timer,
+            game: GameSettings::default(),
+        }
+    }
+}
+
+// everything that is kept between sessions for one player
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    version: u8,
+    pub name: String,
+    // level paths opened up by clearing the level before them in a pack
+    pub unlocked: BTreeSet<String>,
+    pub high_scores: HighScores,
+    // fastest clear in seconds, keyed by level hash
+    pub best_times: HashMap<u64, f32>,
+    pub settings: ProfileSettings,
+    pub bindings: KeyBindings,
+    pub character: Player,
+    pub progress: AchievementProgress,
+}
+
+// read first so a newer profile is turned away before its fields are parsed
+#[derive(Deserialize)]
+struct ProfileVersion {
+    version: u8,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            version: PROFILE_VERSION,
+            name: clean_name(name),
+            unlocked: BTreeSet::new(),
+            high_scores: HighScores::default(),
+            best_times: HashMap::new(),
+            settings: ProfileSettings::default(),
+            bindings: KeyBindings::default(),
+            character: Player::Mask,
+            progress: AchievementProgress::default(),
+        }
+    }
+
+    fn path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{}.ron", name))
+    }
+
+    fn read(path: &Path) -> Result<Profile, anyhow::Error> {
+        let data = std::fs::read_to_string(path)?;
+        match ron::from_str::<ProfileVersion>(&data)?.version {
+            PROFILE_VERSION => Ok(ron::from_str(&data)?),
+            v => Err(anyhow::anyhow!("Unsuported profile version: {}", v)),
+        }
+    }
+
+    // falls back to the backup if the main file is missing or broken
+    pub fn load(dir: &Path, name: &str) -> Result<Profile, anyhow::Error> {
+        let path = Profile::path(dir, name);
+        Profile::read(&path).or_else(|e| {
+            warn!("Failed to load profile {}, trying backup: {}", name, e);
+            Profile::read(&path.with_extension("ron.bak"))
+        })
+    }
+
+    // writes to a temp file and renames it over the old one so a crash
+    // can't leave a half written profile, the old one is kept as a backup
+    pub fn save(&self, dir: &Path) -> Result<(), anyhow::Error> {
+        std::fs::create_dir_all(dir)?;
+        let path = Profile::path(dir, &self.name);
+        let temp = path.with_extension("ron.tmp");
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(data.as_bytes())?;
+        file.sync_all()?;
+        if path.exists() {
+            std::fs::copy(&path, path.with_extension("ron.bak"))?;
+        }
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    // keeps the faster of the two, true if time was a new best
+    pub fn submit_time(&mut self, level_hash: u64, time: f32) -> bool {
+        let best = self.best_times.entry(level_hash).or_insert(f32::INFINITY);
+        if time < *best {
+            *best = time;
+            true
+        } else {
+            false
+        }
+    }
+
+    // the first level of a pack is always open, the rest once the one before is cleared
+    pub fn has_unlocked(&self, pack: &LevelPack, path: &str) -> bool {
+        match pack.index_of(path) {
+            Some(0) | None => true,
+            Some(_) => self.unlocked.contains(path),
+        }
+    }
+}
+
+// the profiles on disk, and which one the profile menu is pointing at
+#[derive(Resource)]
+pub struct ProfileList {
+    pub dir: PathBuf,
+    pub names: Vec<String>,
+    pub selected: usize,
+}
+
+impl ProfileList {
+    pub fn scan(dir: PathBuf) -> ProfileList {
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    // skips the .bak and .tmp files
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "ron"))
+                    .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        ProfileList {
+            dir,
+            names,
+            selected: 0,
+        }
+    }
+
+    fn active(&self) -> Option<String> {
+        let name = std::fs::read_to_string(self.dir.join(ACTIVE_FILE)).ok()?;
+        let name = name.trim();
+        self.names.iter().any(|n| n == name).then(|| name.to_string())
+    }
+
+    fn set_active(&self, name: &str) -> Result<(), anyhow::Error> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(ACTIVE_FILE), name)?;
+        Ok(())
+    }
+
+    pub fn next(&mut self) {
+        if !self.names.is_empty() {
+            self.selected = (self.selected + 1) % self.names.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.names.is_empty() {
+            self.selected = (self.selected + self.names.len() - 1) % self.names.len();
+        }
+    }
+}
+
+#[derive(Event)]
+struct ProfileLoaded;
+
+// makes profile the one being played and hands its values to the game
+pub fn switch_profile(world: &mut World, profile: Profile) {
+    let settings = profile.settings.clone();
+    *world.resource_mut::<GameSettings>() = settings.game;
+    *world.resource_mut::<HighScores>() = profile.high_scores.clone();
+    *world.resource_mut::<AchievementProgress>() = profile.progress.clone();
+    *world.resource_mut::<KeyBindings>() = profile.bindings.clone();
+    *world.resource_mut::<GhostPreset>() = settings.ghost_preset;
+    world.resource_mut::<InputSettings>().deadzone = settings.deadzone;
+    world.resource_mut::<SpeedrunSettings>().show_timer = settings.show_timer;
+    world.resource_mut::<PlayerName>().0 = profile.name.clone();
+    let mut list = world.resource_mut::<ProfileList>();
+    if !list.names.contains(&profile.name) {
+        list.names.push(profile.name.clone());
+        list.names.sort();
+    }
+    list.selected = list.names.iter().position(|n| *n == profile.name).unwrap_or_default();
+    if let Err(e) = list.set_active(&profile.name) {
+        error!("Failed to set active profile: {}", e);
+    }
+    info!("Playing as {}", profile.name);
+    world.insert_resource(profile);
+    world.send_event(ProfileLoaded);
+}
+
+// the first profile picks up any saves from before profiles
+fn first_profile() -> Profile {
+    let mut profile = Profile::new(&PlayerName::default().0);
+    profile.high_scores = HighScores::load_or_default(Path::new(LEGACY_HIGH_SCORES));
+    profile.progress = AchievementProgress::load_or_default(Path::new(LEGACY_ACHIEVEMENTS));
+    profile
+}
+
+fn load_active_profile(world: &mut World) {
+    let list = world.resource::<ProfileList>();
+    let name = list.active().or_else(|| list.names.first().cloned());
+    let profile = match name {
+        Some(name) => Profile::load(&list.dir, &name).unwrap_or_else(|e| {
+            error!("Failed to load profile {}: {}", name, e);
+            Profile::new(&name)
+        }),
+        None => first_profile(),
+    };
+    switch_profile(world, profile);
+}
+
+fn apply_character(
+    mut loaded: EventReader<ProfileLoaded>,
+    profile: Res<Profile>,
+    mut player: Query<&mut Player, With<RealPlayer>>,
+) {
+    if loaded.iter().count() == 0 {
+        return;
+    }
+    for mut player in &mut player {
+        *player = profile.character;
+    }
+}
+
+fn record_clear(
+    mut profile: ResMut<Profile>,
+    summary: Res<LevelSummary>,
+    level_hash: Res<LevelHash>,
+    current_pack: Res<CurrentPack>,
+    packs: Res<Assets<LevelPack>>,
+) {
+    if let (Some(hash), false) = (level_hash.0, summary.assisted) {
+        profile.submit_time(hash, summary.time);
+    }
+    if let Some((_, path)) = current_pack.next(&packs) {
+        profile.unlocked.insert(path.to_string());
+    }
+}
+
+// copies anything the game changed back into the profile and saves it
+fn sync_profile(
+    mut profile: ResMut<Profile>,
+    list: Res<ProfileList>,
+    high_scores: Res<HighScores>,
+    progress: Res<AchievementProgress>,
+    bindings: Res<KeyBindings>,
+    preset: Res<GhostPreset>,
+    input: Res<InputSettings>,
+    speedrun: Res<SpeedrunSettings>,
+    game: Res<GameSettings>,
+    player: Query<&Player, (With<RealPlayer>, Changed<Player>)>,
+) {
+    let character = player.get_single().ok();
+    let changed = high_scores.is_changed()
+        || progress.is_changed()
+        || bindings.is_changed()
+        || preset.is_changed()
+        || input.is_changed()
+        || speedrun.is_changed()
+        || game.is_changed()
+        || character.is_some();
+    if !changed && !profile.is_changed() {
+        return;
+    }
+    profile.high_scores = high_scores.clone();
+    profile.progress = progress.clone();
+    profile.bindings = bindings.clone();
+    profile.settings = ProfileSettings {
+        ghost_preset: *preset,
+        deadzone: input.deadzone,
+        show_timer: speedrun.show_timer,
+        game: game.clone(),
+    };
+    if let Some(character) = character {
+        profile.character = *character;
+    }
+    if let Err(e) = profile.save(&list.dir) {
+        error!("Failed to save profile: {}", e);
+    }
+}
+
+#[test]
+fn profile_saves_atomically_with_backup() {
+    let dir = std::env::temp_dir().join(format!("profile_test_{}", std::process::id()));
+    let mut profile = Profile::new("Test/Player");
+    assert_eq!(profile.name, "Test_Player");
+    assert!(profile.submit_time(1, 12.));
+    assert!(!profile.submit_time(1, 15.));
+    profile.save(&dir).expect("first save");
+    profile.character = Player::Pink;
+    profile.save(&dir).expect("second save");
+    assert!(!dir.join("Test_Player.ron.tmp").exists());
+
+    let loaded = Profile::load(&dir, "Test_Player").expect("load profile");
+    assert_eq!(loaded.character, Player::Pink);
+    assert_eq!(loaded.best_times.get(&1), Some(&12.));
+
+    // a broken main file falls back to the previous save
+    std::fs::write(dir.join("Test_Player.ron"), "not a profile").expect("break profile");
+    let backup = Profile::load(&dir, "Test_Player").expect("load backup");
+    assert_eq!(backup.character, Player::Mask);
+    assert_eq!(ProfileList::scan(dir.clone()).names, vec!["Test_Player"]);
+    std::fs::remove_dir_all(dir).expect("clean up");
+}
+
+#[test]
+fn pack_levels_unlock_in_order() {
+    let pack = LevelPack(vec!["a.lvl.ron".into(), "b.lvl.ron".into()]);
+    let mut profile = Profile::new("Test");
+    assert!(profile.has_unlocked(&pack, "a.lvl.ron"));
+    assert!(!profile.has_unlocked(&pack, "b.lvl.ron"));
+    // levels from outside the pack are never locked
+    assert!(profile.has_unlocked(&pack, "c.lvl.ron"));
+    profile.unlocked.insert("b.lvl.ron".into());
+    assert!(profile.has_unlocked(&pack, "b.lvl.ron"));
+}
+
+#[test]
+fn profile_from_a_newer_version_is_refused() {
+    let dir = std::env::temp_dir().join(format!("profile_version_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("make dir");
+    // fields this version doesn't know about yet
+    std::fs::write(dir.join("Future.ron"), "(version: 9, name: \"Future\", hat: Some(1))")
+        .expect("write profile");
+    let error = Profile::read(&dir.join("Future.ron")).err().expect("newer profile was loaded");
+    assert!(error.to_string().contains("version: 9"));
+    std::fs::remove_dir_all(dir).expect("clean up");
+}
@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::RapierConfiguration;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    map::{load_map, Level, LoadedLevel, MapData},
+    pause::resumed,
+    player::RealPlayer,
+    settings::{GameSettings, GRAVITY},
+    user_input::PlayerInput,
+    GameState,
+};
+
+const REPLAY_VERSION: u8 = 1;
+const REPLAY_DIR: &str = "replays";
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayState>()
+            .add_systems(Startup, replay_from_args)
+            .add_systems(OnEnter(GameState::Play), start_session.run_if(not(resumed)))
+            .add_systems(
+                PreUpdate,
+                (play_inputs, record_inputs)
+                    .chain()
+                    .in_set(InputManagerSystem::ManualControl)
+                    .run_if(in_state(GameState::Play)),
+            )
+            .add_systems(Update, capture_level.before(load_map))
+            .add_event::<SaveReplay>()
+            .add_systems(Update, save_replay_key.run_if(in_state(GameState::Play)))
+            .add_systems(Update, save_replay.after(save_replay_key));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u8,
+    pub level_hash: u64,
+    pub seed: u64,
+    pub level: String,
+    // the gravity assist it was played with, version 0 replays were all full gravity
+    #[serde(default = "full_gravity")]
+    pub gravity_scale: f32,
+    // (pressed actions, number of ticks)
+    pub inputs: Vec<(u8, u32)>,
+}
+
+fn full_gravity() -> f32 {
+    1.
+}
+
+impl Replay {
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, anyhow::Error> {
+        let replay: Replay = ron::de::from_bytes(&std::fs::read(path)?)?;
+        match replay.version {
+            0 | REPLAY_VERSION => Ok(replay),
+            v => Err(anyhow::anyhow!("Unsuported replay version: {}", v)),
+        }
+    }
+
+    pub fn level(&self) -> Result<Level, anyhow::Error> {
+        let level = Level::from_base64(&self.level)?;
+        let hash = level.content_hash()?;
+        if hash != self.level_hash {
+            return Err(anyhow::anyhow!(
+                "Level hash {:x} does not match replay {:x}",
+                hash,
+                self.level_hash
+            ));
+        }
+        Ok(level)
+    }
+
+    pub fn ticks(&self) -> impl Iterator<Item = u8> + '_ {
+        self.inputs
+            .iter()
+            .flat_map(|(mask, count)| std::iter::repeat(*mask).take(*count as usize))
+    }
+
+    fn push(&mut self, mask: u8) {
+        match self.inputs.last_mut() {
+            Some((last, count)) if *last == mask => *count += 1,
+            _ => self.inputs.push((mask, 1)),
+        }
+    }
+}
+
+fn to_mask(state: &ActionState<PlayerInput>) -> u8 {
+    PlayerInput::variants()
+        .filter(|action| state.pressed(action.clone()))
+        .fold(0, |mask, action| mask | 1 << action.index())
+}
+
+#[derive(Resource, Default)]
+enum ReplayState {
+    #[default]
+    Idle,
+    Pending(PathBuf),
+    Recording(Replay),
+    Playing {
+        ticks: Vec<u8>,
+        tick: usize,
+        input_map: Option<InputMap<PlayerInput>>,
+    },
+}
+
+fn replay_from_args(mut state: ResMut<ReplayState>, mut next: ResMut<NextState<GameState>>) {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay").skip(1);
+    if let Some(path) = args.next() {
+        *state = ReplayState::Pending(path.into());
+        next.set(GameState::Play);
+    }
+}
+
+fn start_session(
+    mut state: ResMut<ReplayState>,
+    mut map_data: ResMut<MapData>,
+    mut loaded_level: ResMut<LoadedLevel>,
+    mut levels: ResMut<Assets<Level>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    settings: Res<GameSettings>,
+) {
+    if let ReplayState::Pending(path) = &*state {
+        match Replay::load(path).and_then(|replay| Ok((replay.level()?, replay))) {
+            Ok((level, replay)) => {
+                info!("Playing replay {:?}", path);
+                map_data.reseed(replay.seed);
+                rapier_config.gravity = Vec2::Y * -GRAVITY * replay.gravity_scale;
+                loaded_level.0 = levels.add(level);
+                *state = ReplayState::Playing {
+                    ticks: replay.ticks().collect(),
+                    tick: 0,
+                    input_map: None,
+                };
+                return;
+            }
+            Err(e) => error!("Failed to load replay {:?}: {}", path, e),
+        }
+    }
+    map_data.reseed(rand::random());
+    // respawn the level so anything random uses the new seed
+    loaded_level.set_changed();
+    *state = ReplayState::Recording(Replay {
+        version: REPLAY_VERSION,
+        level_hash: 0,
+        seed: map_data.seed(),
+        level: String::new(),
+        gravity_scale: settings.gravity_scale,
+        inputs: Vec::new(),
+    });
+}
+
+// the recording keeps the level it started on, a different level closes it and starts a new one
+fn capture_level(
+    mut state: ResMut<ReplayState>,
+    mut map_data: ResMut<MapData>,
+    loaded_level: Res<LoadedLevel>,
+    levels: Res<Assets<Level>>,
+    settings: Res<GameSettings>,
+) {
+    let ReplayState::Recording(replay) = &mut *state else {return;};
+    if !replay.level.is_empty() && !loaded_level.is_changed() {
+        return;
+    }
+    let Some(level) = levels.get(&loaded_level.0) else {return;};
+    let (level_hash, data) = match (level.content_hash(), level.to_base64()) {
+        (Ok(level_hash), Ok(data)) => (level_hash, data),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Failed to serialize level: {}", e);
+            return;
+        }
+    };
+    if replay.level.is_empty() {
+        replay.level_hash = level_hash;
+        replay.level = data;
+        return;
+    }
+    // a respawn of the same level keeps recording
+    if replay.level_hash == level_hash {
+        return;
+    }
+    info!("Level changed, closing the replay after {} ticks", replay.ticks().count());
+    // runs before the new level spawns, so it is spawned from the new seed
+    map_data.reseed(rand::random());
+    *replay = Replay {
+        version: REPLAY_VERSION,
+        level_hash,
+        seed: map_data.seed(),
+        level: data,
+        gravity_scale: settings.gravity_scale,
+        inputs: Vec::new(),
+    };
+}
+
+fn record_inputs(
+    mut state: ResMut<ReplayState>,
+    player: Query<&ActionState<PlayerInput>, With<RealPlayer>>,
+) {
+    let ReplayState::Recording(replay) = &mut *state else {return;};
+    let Ok(input) = player.get_single() else {return;};
+    replay.push(to_mask(input));
+}
+
+fn play_inputs(
+    mut commands: Commands,
+    mut state: ResMut<ReplayState>,
+    mut player: Query<(Entity, &mut ActionState<PlayerInput>, Option<&InputMap<PlayerInput>>), With<RealPlayer>>,
+    mut settings: ResMut<GameSettings>,
+) {
+    let ReplayState::Playing { ticks, tick, input_map } = &mut *state else {return;};
+    let Ok((entity, mut action_state, map)) = player.get_single_mut() else {return;};
+    if let Some(map) = map {
+        // stop real input from overwriting the replayed actions
+        *input_map = Some(map.clone());
+        commands.entity(entity).remove::<InputMap<PlayerInput>>();
+    }
+    let Some(mask) = ticks.get(*tick) else {
+        info!("Replay finished after {} ticks", tick);
+        if let Some(map) = input_map.take() {
+            commands.entity(entity).insert(map);
+        }
+        // back to the player's own gravity
+        settings.set_changed();
+        *state = ReplayState::Idle;
+        return;
+    };
+    for action in PlayerInput::variants() {
+        if mask & 1 << action.index() != 0 {
+            action_state.press(action);
+        } else {
+            action_state.release(action);
+        }
+    }
+    *tick += 1;
+}
+
+// writes the replay being recorded to the path
+#[derive(Event)]
+pub struct SaveReplay(pub PathBuf);
+
+impl SaveReplay {
+    // replays/<unix time>.replay.ron
+    pub fn timestamped() -> SaveReplay {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        SaveReplay(Path::new(REPLAY_DIR).join(format!("{}.replay.ron", time)))
+    }
+}
+
+fn save_replay_key(input: Res<Input<KeyCode>>, mut events: EventWriter<SaveReplay>) {
+    if input.just_pressed(KeyCode::F9) {
+        events.send(SaveReplay::timestamped());
+    }
+}
+
+fn save_replay(mut events: EventReader<SaveReplay>, state: Res<ReplayState>) {
+    for SaveReplay(path) in events.iter() {
+        save_recording(path, &state);
+    }
+}
+
+fn save_recording(path: &Path, state: &ReplayState) {
+    let ReplayState::Recording(replay) = state else {warn!("Not recording a replay"); return;};
+    if replay.level.is_empty() {
+        error!("No level loaded");
+        return;
+    }
+    match replay.save(path) {
+        Ok(()) => info!("Saved replay to {:?}", path),
+        Err(e) => error!("Failed to save replay: {}", e),
+    }
+}
+
+#[test]
+fn replay_run_length() {
+    let mut replay = Replay {
+        version: REPLAY_VERSION,
+        level_hash: 0,
+        seed: 0,
+        level: String::new(),
+        gravity_scale: 1.,
+        inputs: Vec::new(),
+    };
+    let ticks = [0, 0, 0, 4, 4, 1, 0];
+    for mask in ticks {
+        replay.push(mask);
+    }
+    assert_eq!(replay.inputs, vec![(0, 3), (4, 2), (1, 1), (0, 1)]);
+    assert_eq!(replay.ticks().collect::<Vec<_>>(), ticks);
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ghost::{BestGhost, Ghost},
+    map::LevelHash,
+    pause::{leaving_play, resumed},
+    settings::GameSettings,
+    GameState, Score,
+};
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerDied>()
+            .init_resource::<LastResult>()
+            .init_resource::<HighScores>()
+            .add_systems(OnEnter(GameState::Play), spawn_hud.run_if(not(resumed)))
+            .add_systems(OnExit(GameState::Play), despawn_hud.run_if(leaving_play))
+            .add_systems(OnExit(GameState::Paused), despawn_hud.run_if(leaving_play))
+            .add_systems(Update, update_hud.run_if(in_state(GameState::Play)))
+            .add_systems(Update, record_death);
+    }
+}
+
+// best score for each level, keyed by the level's content hash,
+// saved as part of the profile
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    scores: HashMap<u64, usize>,
+}
+
+impl HighScores {
+    pub fn load(path: &Path) -> Result<HighScores, anyhow::Error> {
+        Ok(ron::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn load_or_default(path: &Path) -> HighScores {
+        if !path.exists() {
+            return HighScores::default();
+        }
+        HighScores::load(path).unwrap_or_else(|e| {
+            error!("Failed to load high scores: {}", e);
+            HighScores::default()
+        })
+    }
+
+    pub fn get(&self, level_hash: u64) -> usize {
+        self.scores.get(&level_hash).copied().unwrap_or_default()
+    }
+
+    // true if score beat the old best
+    pub fn submit(&mut self, level_hash: u64, score: usize) -> bool {
+        let best = self.scores.entry(level_hash).or_default();
+        if score > *best {
+            *best = score;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct PlayerDied {
+    pub score: usize,
+}
+
+// what the results screen shows
+#[derive(Resource, Default)]
+pub struct LastResult {
+    pub score: usize,
+    pub best: usize,
+    pub new_best: bool,
+    // played with lower gravity, so it wasn't put up as a high score
+    pub assisted: bool,
+}
+
+#[derive(Component)]
+struct ScoreHud;
+
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("Raleway-Regular.ttf"),
+                font_size: 32.,
+                color: Color::WHITE,
+            },
+        )
+        .with_text_alignment(TextAlignment::Right)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            ..Default::default()
+        }),
+        ScoreHud,
+        Name::new("Score Hud"),
+    ));
+}
+
+fn despawn_hud(mut commands: Commands, hud: Query<Entity, With<ScoreHud>>) {
+    for entity in &hud {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_hud(
+    mut hud: Query<&mut Text, With<ScoreHud>>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    level_hash: Res<LevelHash>,
+    ghosts: Query<(), (With<Ghost>, Without<BestGhost>)>,
+) {
+    let best = level_hash.0.map_or(0, |hash| high_scores.get(hash));
+    let value = format!(
+        "Score: {}\nBest: {}\nGhosts: {}",
+        score.0,
+        best.max(score.0),
+        ghosts.iter().count()
+    );
+    for mut text in &mut hud {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn record_death(
+    mut died: EventReader<PlayerDied>,
+    mut high_scores: ResMut<HighScores>,
+    mut result: ResMut<LastResult>,
+    level_hash: Res<LevelHash>,
+    settings: Res<GameSettings>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    let Some(PlayerDied { score }) = died.iter().last() else {return;};
+    let assisted = settings.gravity_assisted();
+    let (best, new_best) = match level_hash.0 {
+        Some(hash) if assisted => (high_scores.get(hash), false),
+        Some(hash) => {
+            let new_best = high_scores.submit(hash, *score);
+            (high_scores.get(hash), new_best)
+        }
+        None => {
+            error!("No level hash for high score");
+            (*score, false)
+        }
+    };
+    *result = LastResult {
+        score: *score,
+        best,
+        new_best,
+        assisted,
+    };
+    next.set(GameState::Results);
+}
+
+#[test]
+fn high_scores_keep_best_per_level() {
+    let mut scores = HighScores::default();
+    assert!(scores.submit(1, 5));
+    assert!(!scores.submit(1, 3));
+    assert!(scores.submit(2, 1));
+    assert_eq!(scores.get(1), 5);
+    assert_eq!(scores.get(2), 1);
+    assert_eq!(scores.get(3), 0);
+
+    let copy: HighScores =
+        ron::from_str(&ron::to_string(&scores).expect("serialize scores")).expect("deserialize scores");
+    assert_eq!(copy.get(1), 5);
+}
@@ -0,0 +1,177 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use bevy_rapier2d::{prelude::*, render::DebugRenderContext};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::SoundCategory,
+    ghost::{GhostPreset, GhostRules},
+};
+
+pub const GRAVITY: f32 = 294.;
+pub const SCALES: [f32; 4] = [1., 1.5, 2., 0.75];
+pub const GRAVITY_SCALES: [f32; 3] = [1., 0.75, 0.5];
+// volume steps the settings menu cycles through
+pub const VOLUMES: [f32; 6] = [1., 0.8, 0.6, 0.4, 0.2, 0.];
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSettings>()
+            .add_systems(
+                Update,
+                (apply_window, apply_physics).run_if(resource_changed::<GameSettings>()),
+            )
+            // after the ghost rules have been picked for the frame
+            .add_systems(
+                PostUpdate,
+                harmless_ghosts.run_if(
+                    resource_changed::<GameSettings>().or_else(resource_changed::<GhostRules>()),
+                ),
+            );
+    }
+}
+
+// everything on the settings screen, saved in the profile
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub window_mode: WindowMode,
+    // multiplies the window's scale factor
+    pub scale: f32,
+    pub debug_physics: bool,
+    // assist, lower is floatier
+    pub gravity_scale: f32,
+    // assist, ghosts still play back but can't kill
+    pub harmless_ghosts: bool,
+    // master volume, each category is scaled by it
+    pub volume: f32,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+    pub ui_volume: f32,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            window_mode: WindowMode::Windowed,
+            scale: 1.,
+            debug_physics: true,
+            gravity_scale: 1.,
+            harmless_ghosts: false,
+            volume: 0.8,
+            music_volume: 1.,
+            effects_volume: 1.,
+            ui_volume: 1.,
+        }
+    }
+}
+
+impl GameSettings {
+    pub fn next_window_mode(&mut self) {
+        self.window_mode = match self.window_mode {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            WindowMode::BorderlessFullscreen => WindowMode::Fullscreen,
+            _ => WindowMode::Windowed,
+        };
+    }
+
+    pub fn next_scale(&mut self) {
+        self.scale = next_step(&SCALES, self.scale);
+    }
+
+    pub fn next_gravity_scale(&mut self) {
+        self.gravity_scale = next_step(&GRAVITY_SCALES, self.gravity_scale);
+    }
+
+    // runs played with lower gravity are kept off high scores, leaderboards and splits
+    pub fn gravity_assisted(&self) -> bool {
+        self.gravity_scale != 1.
+    }
+
+    // None steps the master volume
+    pub fn next_volume(&mut self, category: Option<SoundCategory>) {
+        let volume = match category {
+            None => &mut self.volume,
+            Some(SoundCategory::Music) => &mut self.music_volume,
+            Some(SoundCategory::Effects) => &mut self.effects_volume,
+            Some(SoundCategory::Ui) => &mut self.ui_volume,
+        };
+        *volume = next_step(&VOLUMES, *volume);
+    }
+
+    pub fn volume(&self, category: SoundCategory) -> f32 {
+        self.volume
+            * match category {
+                SoundCategory::Music => self.music_volume,
+                SoundCategory::Effects => self.effects_volume,
+                SoundCategory::Ui => self.ui_volume,
+            }
+    }
+}
+
+// the step after value, the first step if value isn't one of them
+fn next_step(steps: &[f32], value: f32) -> f32 {
+    let index = steps.iter().position(|step| *step == value);
+    steps[index.map_or(0, |i| (i + 1) % steps.len())]
+}
+
+fn apply_window(
+    settings: Res<GameSettings>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = window.get_single_mut() else {return;};
+    window.mode = settings.window_mode;
+    let scale = window.resolution.base_scale_factor() * settings.scale as f64;
+    window.resolution.set_scale_factor_override(Some(scale));
+}
+
+fn apply_physics(
+    settings: Res<GameSettings>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut debug: ResMut<DebugRenderContext>,
+) {
+    rapier_config.gravity = Vec2::Y * -GRAVITY * settings.gravity_scale;
+    debug.enabled = settings.debug_physics;
+}
+
+fn harmless_ghosts(
+    settings: Res<GameSettings>,
+    mut rules: ResMut<GhostRules>,
+    mut preset: ResMut<GhostPreset>,
+    mut was_harmless: Local<bool>,
+) {
+    if settings.harmless_ghosts {
+        if rules.lethal {
+            rules.lethal = false;
+        }
+    } else if *was_harmless {
+        // puts back what the preset says
+        preset.set_changed();
+    }
+    *was_harmless = settings.harmless_ghosts;
+}
+
+#[test]
+fn settings_cycle_steps() {
+    let mut settings = GameSettings::default();
+    settings.next_scale();
+    assert_eq!(settings.scale, 1.5);
+    settings.scale = 3.;
+    settings.next_scale();
+    assert_eq!(settings.scale, 1.);
+    settings.next_volume(None);
+    assert_eq!(settings.volume, 0.6);
+    settings.next_volume(Some(SoundCategory::Music));
+    assert_eq!(settings.volume(SoundCategory::Music), 0.6 * 0.8);
+    settings.next_window_mode();
+    assert_eq!(settings.window_mode, WindowMode::BorderlessFullscreen);
+
+    // profiles from before a setting existed still load
+    let old: GameSettings = ron::from_str("(scale: 2.0)").expect("partial settings");
+    assert_eq!(old.scale, 2.);
+    assert_eq!(old.volume, GameSettings::default().volume);
+}
@@ -0,0 +1,647 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ghost::GoalReached,
+    interaction::InteractionSet,
+    map::{AttemptStarted, Checkpoint, CheckpointReached, LevelHash},
+    pause::{leaving_play, resumed},
+    player::RealPlayer,
+    settings::GameSettings,
+    user_input::PlayerInput,
+    GameState,
+};
+
+const SPLITS_VERSION: u8 = 1;
+const SPLITS_DIR: &str = "saves/splits";
+// LiveSplit Server's default port
+const LIVESPLIT_ADDR: &str = "127.0.0.1:16834";
+// ticks between writes of the split file while running
+const FILE_INTERVAL: usize = 30;
+// batches of commands that can wait for the LiveSplit connection
+const LIVESPLIT_QUEUE: usize = 64;
+
+pub struct SpeedrunPlugin;
+
+impl Plugin for SpeedrunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTimer>()
+            .init_resource::<SpeedrunSettings>()
+            .init_resource::<LiveSplit>()
+            .add_event::<RunEvent>()
+            .add_systems(Startup, settings_from_args)
+            .add_systems(OnEnter(GameState::Play), spawn_timer_text.run_if(not(resumed)))
+            .add_systems(OnExit(GameState::Play), despawn_timer_text.run_if(leaving_play))
+            .add_systems(OnExit(GameState::Paused), despawn_timer_text.run_if(leaving_play))
+            .add_systems(
+                Update,
+                (reset_run, tick_run.run_if(in_state(GameState::Play)), split_run)
+                    .chain()
+                    .after(InteractionSet::Handle),
+            )
+            .add_systems(Update, update_timer_text.after(split_run))
+            .add_systems(Update, (write_split_file, send_livesplit).after(split_run));
+    }
+}
+
+#[derive(Resource)]
+pub struct SpeedrunSettings {
+    pub show_timer: bool,
+    // rewritten as the run goes so other programs can read it
+    pub split_file: Option<PathBuf>,
+    // LiveSplit Server to drive, None to leave it alone
+    pub livesplit: Option<SocketAddr>,
+}
+
+impl Default for SpeedrunSettings {
+    fn default() -> Self {
+        SpeedrunSettings {
+            show_timer: true,
+            split_file: None,
+            livesplit: None,
+        }
+    }
+}
+
+// --split-file <path> and --livesplit [addr]
+fn settings_from_args(mut settings: ResMut<SpeedrunSettings>) {
+    let args: Vec<String> = std::env::args().collect();
+    for (i, arg) in args.iter().enumerate() {
+        let value = args.get(i + 1).filter(|value| !value.starts_with("--"));
+        match arg.as_str() {
+            "--split-file" => match value {
+                Some(path) => settings.split_file = Some(path.into()),
+                None => error!("--split-file needs a path"),
+            },
+            "--livesplit" => {
+                let addr = value.map_or(LIVESPLIT_ADDR, |addr| addr.as_str());
+                match addr.parse() {
+                    Ok(addr) => settings.livesplit = Some(addr),
+                    Err(e) => error!("Bad LiveSplit address {}: {}", addr, e),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// where a split is taken, checkpoints by the cell they sit in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SplitPoint {
+    Start,
+    Checkpoint(IVec2),
+    End,
+}
+
+// the fastest finish down one route, the split points it passed in order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteBest {
+    pub route: Vec<SplitPoint>,
+    pub splits: Vec<usize>,
+}
+
+impl RouteBest {
+    fn time(&self) -> usize {
+        self.splits.last().copied().unwrap_or(usize::MAX)
+    }
+}
+
+// best times for one level, all in physics ticks
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SplitRecord {
+    version: u8,
+    // runs are only compared with others that took the same route
+    pub pbs: Vec<RouteBest>,
+    // shortest time seen between each pair of split points
+    pub golds: HashMap<(SplitPoint, SplitPoint), usize>,
+}
+
+// read first so older records can be told apart
+#[derive(Deserialize)]
+struct SplitVersion {
+    version: u8,
+}
+
+impl SplitRecord {
+    fn path(level_hash: u64) -> PathBuf {
+        Path::new(SPLITS_DIR).join(format!("{:x}.ron", level_hash))
+    }
+
+    pub fn load(level_hash: u64) -> Result<SplitRecord, anyhow::Error> {
+        let path = SplitRecord::path(level_hash);
+        if !path.exists() {
+            return Ok(SplitRecord::default());
+        }
+        let data = std::fs::read_to_string(path)?;
+        match ron::from_str::<SplitVersion>(&data)?.version {
+            0 => {
+                // splits were matched by position, they can't be trusted across routes
+                warn!("Split record from before routes, starting it over");
+                Ok(SplitRecord::default())
+            }
+            SPLITS_VERSION => Ok(ron::from_str(&data)?),
+            v => Err(anyhow::anyhow!("Unsuported split version: {}", v)),
+        }
+    }
+
+    pub fn save(&self, level_hash: u64) -> Result<(), anyhow::Error> {
+        let path = SplitRecord::path(level_hash);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let record = SplitRecord {
+            version: SPLITS_VERSION,
+            ..self.clone()
+        };
+        let data = ron::ser::to_string_pretty(&record, ron::ser::PrettyConfig::default())?;
+        // a crash mid write must not cost the old record
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    // folds a finished run in, true if it was a new personal best for its route
+    fn submit(&mut self, route: &[SplitPoint], splits: &[usize]) -> bool {
+        for (key, segment) in route_segments(route).zip(segments(splits)) {
+            let gold = self.golds.entry(key).or_insert(segment);
+            *gold = (*gold).min(segment);
+        }
+        let Some(time) = splits.last().copied() else {return false;};
+        match self.pbs.iter_mut().find(|pb| pb.route == route) {
+            Some(pb) if time < pb.time() => pb.splits = splits.to_vec(),
+            Some(_) => return false,
+            None => self.pbs.push(RouteBest {
+                route: route.to_vec(),
+                splits: splits.to_vec(),
+            }),
+        }
+        true
+    }
+
+    // the fastest personal best that passed the same split points so far
+    fn pb(&self, route: &[SplitPoint]) -> Option<&RouteBest> {
+        self.pbs
+            .iter()
+            .filter(|pb| pb.route.starts_with(route))
+            .min_by_key(|pb| pb.time())
+    }
+
+    fn gold(&self, key: (SplitPoint, SplitPoint)) -> Option<usize> {
+        self.golds.get(&key).copied()
+    }
+}
+
+// lengths of each segment between splits
+fn segments(splits: &[usize]) -> impl Iterator<Item = usize> + '_ {
+    splits
+        .iter()
+        .scan(0, |last, split| Some(split - std::mem::replace(last, *split)))
+}
+
+// the split points each segment runs between
+fn route_segments(route: &[SplitPoint]) -> impl Iterator<Item = (SplitPoint, SplitPoint)> + '_ {
+    std::iter::once(SplitPoint::Start)
+        .chain(route.iter().copied())
+        .zip(route.iter().copied())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum RunState {
+    #[default]
+    Idle,
+    Running,
+    Finished,
+}
+
+// counts physics ticks from the player's first input in an attempt to the level's End
+#[derive(Resource, Default)]
+pub struct RunTimer {
+    pub state: RunState,
+    pub ticks: usize,
+    pub splits: Vec<usize>,
+    // where each split was taken
+    pub route: Vec<SplitPoint>,
+    level: Option<u64>,
+    record: SplitRecord,
+}
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum RunEvent {
+    Start,
+    Split(usize),
+    Finish(usize),
+    Reset,
+}
+
+// seconds in one physics tick
+pub fn tick_seconds(rapier_config: &RapierConfiguration) -> f32 {
+    match rapier_config.timestep_mode {
+        TimestepMode::Fixed { dt, .. } => dt,
+        _ => 1. / 60.,
+    }
+}
+
+fn reset_run(
+    mut attempts: EventReader<AttemptStarted>,
+    mut timer: ResMut<RunTimer>,
+    mut events: EventWriter<RunEvent>,
+) {
+    if attempts.iter().count() == 0 {
+        return;
+    }
+    if timer.state == RunState::Running {
+        events.send(RunEvent::Reset);
+    }
+    timer.state = RunState::Idle;
+    timer.ticks = 0;
+    timer.splits.clear();
+    timer.route.clear();
+}
+
+fn tick_run(
+    mut timer: ResMut<RunTimer>,
+    mut events: EventWriter<RunEvent>,
+    player: Query<&ActionState<PlayerInput>, With<RealPlayer>>,
+    level_hash: Res<LevelHash>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    // rewinding and pausing stop the physics, so they stop the clock as well
+    if !rapier_config.physics_pipeline_active {
+        return;
+    }
+    match timer.state {
+        RunState::Idle => {
+            // the run starts with the player's first input, not while they wait at the start
+            if player.iter().all(|input| input.get_pressed().is_empty()) {
+                return;
+            }
+            if timer.level != level_hash.0 {
+                timer.level = level_hash.0;
+                timer.record = match level_hash.0.map(SplitRecord::load) {
+                    Some(Ok(record)) => record,
+                    Some(Err(e)) => {
+                        error!("Failed to load splits: {}", e);
+                        SplitRecord::default()
+                    }
+                    None => SplitRecord::default(),
+                };
+            }
+            timer.state = RunState::Running;
+            timer.ticks = 1;
+            events.send(RunEvent::Start);
+        }
+        RunState::Running => timer.ticks += 1,
+        RunState::Finished => {}
+    }
+}
+
+fn split_run(
+    mut reached: EventReader<CheckpointReached>,
+    mut goal: EventReader<GoalReached>,
+    mut timer: ResMut<RunTimer>,
+    mut events: EventWriter<RunEvent>,
+    checkpoints: Query<&Checkpoint>,
+    settings: Res<GameSettings>,
+) {
+    let passed: Vec<SplitPoint> = reached
+        .iter()
+        .filter_map(|CheckpointReached(entity)| checkpoints.get(*entity).ok())
+        .map(|checkpoint| SplitPoint::Checkpoint(checkpoint.position))
+        .collect();
+    let finished = goal.iter().count() > 0;
+    if timer.state != RunState::Running {
+        return;
+    }
+    let ticks = timer.ticks;
+    for point in passed {
+        timer.splits.push(ticks);
+        timer.route.push(point);
+        events.send(RunEvent::Split(ticks));
+    }
+    if !finished {
+        return;
+    }
+    timer.splits.push(ticks);
+    timer.route.push(SplitPoint::End);
+    timer.state = RunState::Finished;
+    events.send(RunEvent::Finish(ticks));
+    if settings.gravity_assisted() {
+        info!("Gravity assist on, splits not saved");
+        return;
+    }
+    let Some(level) = timer.level else {return;};
+    let (route, splits) = (timer.route.clone(), timer.splits.clone());
+    if timer.record.submit(&route, &splits) {
+        info!("New personal best: {} ticks", ticks);
+    }
+    if let Err(e) = timer.record.save(level) {
+        error!("Failed to save splits: {}", e);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Gold,
+    Ahead,
+    Behind,
+    // nothing to compare against yet
+    Unknown,
+}
+
+impl Comparison {
+    fn color(&self) -> Color {
+        match self {
+            Comparison::Gold => Color::GOLD,
+            Comparison::Ahead => Color::GREEN,
+            Comparison::Behind => Color::RED,
+            Comparison::Unknown => Color::WHITE,
+        }
+    }
+}
+
+fn compare(split: usize, segment: usize, pb: Option<usize>, gold: Option<usize>) -> Comparison {
+    if gold.map_or(false, |gold| segment < gold) {
+        return Comparison::Gold;
+    }
+    match pb {
+        Some(pb) if split <= pb => Comparison::Ahead,
+        Some(_) => Comparison::Behind,
+        None => Comparison::Unknown,
+    }
+}
+
+fn format_ticks(ticks: usize, dt: f32) -> String {
+    let seconds = ticks as f32 * dt;
+    let minutes = (seconds / 60.).floor();
+    format!("{}:{:05.2}", minutes, seconds - minutes * 60.)
+}
+
+fn format_delta(ticks: usize, pb: usize, dt: f32) -> String {
+    format!("{:+.2}", (ticks as f32 - pb as f32) * dt)
+}
+
+#[derive(Component)]
+struct TimerText(Handle<Font>);
+
+fn spawn_timer_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<SpeedrunSettings>,
+) {
+    if !settings.show_timer {
+        return;
+    }
+    commands.spawn((
+        TextBundle::default()
+            .with_text_alignment(TextAlignment::Right)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                right: Val::Px(10.),
+                ..Default::default()
+            }),
+        TimerText(asset_server.load("Raleway-Regular.ttf")),
+        Name::new("Speedrun Timer"),
+    ));
+}
+
+fn despawn_timer_text(mut commands: Commands, text: Query<Entity, With<TimerText>>) {
+    for entity in &text {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_timer_text(
+    mut text: Query<(&mut Text, &TimerText)>,
+    timer: Res<RunTimer>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    let Ok((mut text, font)) = text.get_single_mut() else {return;};
+    if !timer.is_changed() {
+        return;
+    }
+    let dt = tick_seconds(&rapier_config);
+    let style = |size: f32, color: Color| TextStyle {
+        font: font.0.clone(),
+        font_size: size,
+        color,
+    };
+    let mut sections = Vec::new();
+    let record = &timer.record;
+    let pb_splits = record.pb(&timer.route).map_or(&[][..], |pb| &pb.splits[..]);
+    for (i, ((split, segment), key)) in timer
+        .splits
+        .iter()
+        .zip(segments(&timer.splits))
+        .zip(route_segments(&timer.route))
+        .enumerate()
+    {
+        let pb = pb_splits.get(i).copied();
+        let comparison = compare(*split, segment, pb, record.gold(key));
+        let delta = pb.map_or(String::new(), |pb| format_delta(*split, pb, dt));
+        sections.push(TextSection::new(
+            format!("{}  {}  {}\n", i + 1, format_ticks(*split, dt), delta),
+            style(24., comparison.color()),
+        ));
+    }
+    // what is still to come in the personal best
+    for (i, pb) in pb_splits.iter().enumerate().skip(timer.splits.len()) {
+        sections.push(TextSection::new(
+            format!("{}  {}\n", i + 1, format_ticks(*pb, dt)),
+            style(24., Color::GRAY),
+        ));
+    }
+    let finished = timer.state == RunState::Finished;
+    // the next personal best split to beat, or the final time once done
+    let target = if finished {
+        pb_splits.last()
+    } else {
+        pb_splits.get(timer.splits.len())
+    };
+    let color = match target {
+        Some(pb) if timer.ticks > *pb => Color::RED,
+        Some(_) if finished => Color::GREEN,
+        _ => Color::WHITE,
+    };
+    sections.push(TextSection::new(format_ticks(timer.ticks, dt), style(40., color)));
+    text.sections = sections;
+}
+
+// what the split file holds
+#[derive(Serialize)]
+struct SplitState<'a> {
+    state: RunState,
+    ticks: usize,
+    seconds: f32,
+    splits: &'a [usize],
+    route: &'a [SplitPoint],
+    // the personal best this run is racing, and the golds along its route
+    pb: &'a [usize],
+    golds: Vec<Option<usize>>,
+}
+
+fn write_split_file(
+    mut events: EventReader<RunEvent>,
+    timer: Res<RunTimer>,
+    settings: Res<SpeedrunSettings>,
+    rapier_config: Res<RapierConfiguration>,
+    mut written: Local<usize>,
+) {
+    let Some(path) = &settings.split_file else {return;};
+    let changed = events.iter().count() > 0;
+    let periodic = timer.state == RunState::Running
+        && timer.ticks % FILE_INTERVAL == 0
+        && timer.ticks != *written;
+    if !changed && !periodic {
+        return;
+    }
+    *written = timer.ticks;
+    let pb = timer.record.pb(&timer.route);
+    let state = SplitState {
+        state: timer.state,
+        ticks: timer.ticks,
+        seconds: timer.ticks as f32 * tick_seconds(&rapier_config),
+        splits: &timer.splits,
+        route: &timer.route,
+        pb: pb.map_or(&[][..], |pb| &pb.splits[..]),
+        golds: route_segments(pb.map_or(&timer.route[..], |pb| &pb.route[..]))
+            .map(|key| timer.record.gold(key))
+            .collect(),
+    };
+    let result = ron::ser::to_string_pretty(&state, ron::ser::PrettyConfig::default())
+        .map_err(anyhow::Error::from)
+        .and_then(|data| {
+            // readers never see a half written file
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, data)?;
+            std::fs::rename(tmp, path)?;
+            Ok(())
+        });
+    if let Err(e) = result {
+        error!("Failed to write split file: {}", e);
+    }
+}
+
+// the connection lives on its own thread so connecting or writing never stalls a frame
+#[derive(Resource, Default)]
+struct LiveSplit(Option<(SocketAddr, SyncSender<Vec<String>>)>);
+
+impl LiveSplit {
+    fn send(&mut self, addr: SocketAddr, commands: Vec<String>) {
+        if self.0.as_ref().map_or(true, |(to, _)| *to != addr) {
+            let (sender, receiver) = mpsc::sync_channel(LIVESPLIT_QUEUE);
+            std::thread::spawn(move || livesplit_worker(addr, receiver));
+            self.0 = Some((addr, sender));
+        }
+        let Some((_, sender)) = &self.0 else {return;};
+        match sender.try_send(commands) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => warn!("LiveSplit is falling behind, dropped commands"),
+            Err(TrySendError::Disconnected(_)) => self.0 = None,
+        }
+    }
+}
+
+// stops once the sender is dropped
+fn livesplit_worker(addr: SocketAddr, batches: Receiver<Vec<String>>) {
+    let mut stream: Option<TcpStream> = None;
+    for commands in batches {
+        if stream.is_none() {
+            match TcpStream::connect_timeout(&addr, Duration::from_millis(100)) {
+                Ok(connected) => {
+                    info!("Connected to LiveSplit at {}", addr);
+                    stream = Some(connected);
+                }
+                Err(e) => {
+                    warn!("Could not reach LiveSplit at {}: {}", addr, e);
+                    continue;
+                }
+            }
+        }
+        let Some(connected) = &mut stream else {continue;};
+        for command in commands {
+            if let Err(e) = connected.write_all(format!("{}\r\n", command).as_bytes()) {
+                warn!("Lost LiveSplit connection: {}", e);
+                stream = None;
+                break;
+            }
+        }
+    }
+}
+
+// LiveSplit runs on game time so its splits match the tick count exactly
+fn livesplit_commands(event: RunEvent, dt: f32) -> Vec<String> {
+    let game_time = |ticks: usize| format!("setgametime {:.3}", ticks as f32 * dt);
+    match event {
+        RunEvent::Start => vec![
+            "reset".into(),
+            "starttimer".into(),
+            "initgametime".into(),
+            "pausegametime".into(),
+        ],
+        RunEvent::Split(ticks) | RunEvent::Finish(ticks) => vec![game_time(ticks), "split".into()],
+        RunEvent::Reset => vec!["reset".into()],
+    }
+}
+
+fn send_livesplit(
+    mut events: EventReader<RunEvent>,
+    settings: Res<SpeedrunSettings>,
+    mut livesplit: ResMut<LiveSplit>,
+    rapier_config: Res<RapierConfiguration>,
+) {
+    let Some(addr) = settings.livesplit else {
+        events.clear();
+        return;
+    };
+    let dt = tick_seconds(&rapier_config);
+    for event in events.iter() {
+        livesplit.send(addr, livesplit_commands(*event, dt));
+    }
+}
+
+#[test]
+fn split_record_tracks_pb_and_golds() {
+    let (a, b) = (SplitPoint::Checkpoint(IVec2::X), SplitPoint::Checkpoint(IVec2::Y));
+    let route = [a, b, SplitPoint::End];
+    let golds = |record: &SplitRecord, route: &[SplitPoint]| -> Vec<Option<usize>> {
+        route_segments(route).map(|key| record.gold(key)).collect()
+    };
+    let mut record = SplitRecord::default();
+    assert!(record.submit(&route, &[100, 250, 400]));
+    assert_eq!(golds(&record, &route), vec![Some(100), Some(150), Some(150)]);
+    // slower overall but with a faster middle segment
+    assert!(!record.submit(&route, &[120, 240, 420]));
+    assert_eq!(record.pb(&route).unwrap().splits, vec![100, 250, 400]);
+    assert_eq!(golds(&record, &route), vec![Some(100), Some(120), Some(150)]);
+    assert!(record.submit(&route, &[110, 240, 380]));
+    assert_eq!(record.pb(&route).unwrap().splits, vec![110, 240, 380]);
+    assert_eq!(golds(&record, &route), vec![Some(100), Some(120), Some(140)]);
+
+    // skipping a checkpoint is its own route, it neither beats nor borrows the other's splits
+    let skip = [b, SplitPoint::End];
+    assert_eq!(record.pb(&skip), None);
+    assert!(record.submit(&skip, &[300, 500]));
+    assert_eq!(record.pb(&route).unwrap().splits, vec![110, 240, 380]);
+    assert_eq!(golds(&record, &skip), vec![Some(300), Some(140)]);
+    assert!(!record.submit(&skip, &[310, 520]));
+    assert_eq!(record.pb(&[]).unwrap().route, route.to_vec());
+
+    assert_eq!(compare(90, 90, Some(100), Some(100)), Comparison::Gold);
+    assert_eq!(compare(250, 150, Some(250), Some(120)), Comparison::Ahead);
+    assert_eq!(compare(260, 150, Some(250), Some(120)), Comparison::Behind);
+    assert_eq!(compare(260, 150, None, None), Comparison::Unknown);
+    assert_eq!(format_ticks(3690, 1. / 60.), "1:01.50");
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+const COLUMNS: usize = 16;
+const ROWS: usize = 9;
+// seconds to cover the screen, uncovering takes the same again
+const WIPE_TIME: f32 = 0.5;
+// how much of the wipe the last tile waits before it starts growing
+const WIPE_SPREAD: f32 = 0.5;
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StartWipe>()
+            .add_systems(Update, (start_wipe, update_wipe).chain());
+    }
+}
+
+// covers the screen, runs the action while nothing can be seen, then uncovers
+#[derive(Event)]
+pub struct StartWipe(pub fn(&mut World));
+
+#[derive(Component)]
+struct Wipe {
+    // 0..1 covering, 1..2 uncovering
+    progress: f32,
+    action: Option<fn(&mut World)>,
+}
+
+#[derive(Component)]
+struct WipeTile {
+    // 0 for the first tile to move, 1 for the last
+    delay: f32,
+}
+
+fn tile_scale(progress: f32, delay: f32) -> f32 {
+    let grow = |t: f32| ((t - delay * WIPE_SPREAD) / (1. - WIPE_SPREAD)).clamp(0., 1.);
+    if progress <= 1. {
+        grow(progress)
+    } else {
+        1. - grow(progress - 1.)
+    }
+}
+
+fn start_wipe(
+    mut commands: Commands,
+    mut events: EventReader<StartWipe>,
+    wipes: Query<(), With<Wipe>>,
+    asset_server: Res<AssetServer>,
+) {
+    for StartWipe(action) in events.iter() {
+        if !wipes.is_empty() {
+            warn!("Screen wipe already running");
+            continue;
+        }
+        let image = asset_server.load("Other/Transition.png");
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        flex_wrap: FlexWrap::Wrap,
+                        ..Default::default()
+                    },
+                    z_index: ZIndex::Global(100),
+                    ..Default::default()
+                },
+                Wipe {
+                    progress: 0.,
+                    action: Some(*action),
+                },
+                Name::new("Screen Wipe"),
+            ))
+            .with_children(|root| {
+                for y in 0..ROWS {
+                    for x in 0..COLUMNS {
+                        root.spawn((
+                            ImageBundle {
+                                style: Style {
+                                    width: Val::Percent(100. / COLUMNS as f32),
+                                    height: Val::Percent(100. / ROWS as f32),
+                                    ..Default::default()
+                                },
+                                image: UiImage::new(image.clone()),
+                                transform: Transform::from_scale(Vec3::ZERO),
+                                ..Default::default()
+                            },
+                            // sweeps from the top left corner
+                            WipeTile {
+                                delay: (x + y) as f32 / (COLUMNS + ROWS - 2) as f32,
+                            },
+                        ));
+                    }
+                }
+            });
+    }
+}
+
+fn update_wipe(
+    mut commands: Commands,
+    mut wipes: Query<(Entity, &mut Wipe, &Children)>,
+    mut tiles: Query<(&WipeTile, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut wipe, children) in &mut wipes {
+        wipe.progress += time.delta_seconds() / WIPE_TIME;
+        if wipe.progress >= 1. {
+            if let Some(action) = wipe.action.take() {
+                commands.add(action);
+            }
+        }
+        if wipe.progress >= 2. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        for child in children.iter() {
+            let Ok((tile, mut transform)) = tiles.get_mut(*child) else {continue;};
+            transform.scale = Vec3::splat(tile_scale(wipe.progress, tile.delay));
+        }
+    }
+}
+
+#[test]
+fn wipe_covers_then_uncovers() {
+    assert_eq!(tile_scale(0., 0.), 0.);
+    assert_eq!(tile_scale(1., 0.), 1.);
+    assert_eq!(tile_scale(1., 1.), 1.);
+    // the last tile is still waiting when the first is half grown
+    assert_eq!(tile_scale(0.25, 0.), 0.5);
+    assert_eq!(tile_scale(0.25, 1.), 0.);
+    assert_eq!(tile_scale(1.5, 0.), 0.);
+    assert_eq!(tile_scale(2., 1.), 0.);
+}
@@ -1,7 +1,33 @@
-use bevy::{prelude::KeyCode, reflect::TypePath};
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadConnectionEvent},
+    prelude::*,
+    reflect::TypePath,
+};
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Actionlike, Clone, TypePath)]
+pub struct UserInputPlugin;
+
+impl Plugin for UserInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(InputManagerPlugin::<PlayerInput>::default())
+            .add_plugins(InputManagerPlugin::<MenuInput>::default())
+            .init_resource::<InputSettings>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<ActionState<MenuInput>>()
+            .insert_resource(MenuInput::default_map())
+            .add_systems(PreUpdate, assign_gamepads)
+            .add_systems(
+                Update,
+                apply_input_settings.run_if(
+                    resource_changed::<InputSettings>()
+                        .or_else(resource_changed::<KeyBindings>()),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Actionlike, Clone, Copy, PartialEq, TypePath, Serialize, Deserialize)]
 pub enum PlayerInput {
     Left,
     Right,
@@ -9,12 +35,36 @@ pub enum PlayerInput {
     Fall,
     NextPlayer,
     PevPlayer,
+    Rewind,
 }
 
-impl PlayerInput {
-    pub fn player_one() -> InputMap<PlayerInput> {
-        let mut map = InputMap::default();
-        map.insert_multiple([
+#[derive(Debug, Actionlike, Clone, TypePath)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Confirm,
+    Back,
+    Pause,
+}
+
+#[derive(Resource)]
+pub struct InputSettings {
+    pub deadzone: f32,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings { deadzone: 0.25 }
+    }
+}
+
+// keyboard keys for the player's actions, kept in the profile
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings(pub Vec<(KeyCode, PlayerInput)>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings(vec![
             (KeyCode::A, PlayerInput::Left),
             (KeyCode::Left, PlayerInput::Left),
             (KeyCode::D, PlayerInput::Right),
@@ -26,7 +76,116 @@ impl PlayerInput {
             (KeyCode::Down, PlayerInput::Fall),
             (KeyCode::Q, PlayerInput::PevPlayer),
             (KeyCode::E, PlayerInput::NextPlayer),
+            (KeyCode::R, PlayerInput::Rewind),
+        ])
+    }
+}
+
+impl PlayerInput {
+    pub fn player_one() -> InputMap<PlayerInput> {
+        PlayerInput::player_one_with(&InputSettings::default(), &KeyBindings::default())
+    }
+
+    pub fn player_one_with(settings: &InputSettings, bindings: &KeyBindings) -> InputMap<PlayerInput> {
+        let deadzone = settings.deadzone;
+        let mut map = InputMap::default();
+        map.insert_multiple(bindings.0.iter().copied());
+        map.insert_multiple([
+            (GamepadButtonType::DPadLeft, PlayerInput::Left),
+            (GamepadButtonType::DPadRight, PlayerInput::Right),
+            (GamepadButtonType::DPadDown, PlayerInput::Fall),
+            (GamepadButtonType::South, PlayerInput::Jump),
+            (GamepadButtonType::East, PlayerInput::Fall),
+            (GamepadButtonType::West, PlayerInput::PevPlayer),
+            (GamepadButtonType::North, PlayerInput::NextPlayer),
+            (GamepadButtonType::LeftTrigger2, PlayerInput::Rewind),
         ]);
+        map.insert(
+            SingleAxis::negative_only(GamepadAxisType::LeftStickX, -deadzone),
+            PlayerInput::Left,
+        );
+        map.insert(
+            SingleAxis::positive_only(GamepadAxisType::LeftStickX, deadzone),
+            PlayerInput::Right,
+        );
+        map.insert(
+            SingleAxis::negative_only(GamepadAxisType::LeftStickY, -deadzone),
+            PlayerInput::Fall,
+        );
         map
     }
 }
+
+impl MenuInput {
+    pub fn default_map() -> InputMap<MenuInput> {
+        let mut map = InputMap::default();
+        map.insert_multiple([
+            (KeyCode::Up, MenuInput::Up),
+            (KeyCode::Down, MenuInput::Down),
+            (KeyCode::Return, MenuInput::Confirm),
+            (KeyCode::Escape, MenuInput::Pause),
+            (KeyCode::Escape, MenuInput::Back),
+            (KeyCode::Back, MenuInput::Back),
+        ]);
+        map.insert_multiple([
+            (GamepadButtonType::DPadUp, MenuInput::Up),
+            (GamepadButtonType::DPadDown, MenuInput::Down),
+            (GamepadButtonType::South, MenuInput::Confirm),
+            (GamepadButtonType::East, MenuInput::Back),
+            (GamepadButtonType::Start, MenuInput::Pause),
+        ]);
+        map.insert(
+            SingleAxis::positive_only(GamepadAxisType::LeftStickY, 0.5),
+            MenuInput::Up,
+        );
+        map.insert(
+            SingleAxis::negative_only(GamepadAxisType::LeftStickY, -0.5),
+            MenuInput::Down,
+        );
+        map
+    }
+}
+
+fn apply_input_settings(
+    settings: Res<InputSettings>,
+    bindings: Res<KeyBindings>,
+    mut players: Query<&mut InputMap<PlayerInput>>,
+) {
+    for mut map in &mut players {
+        let gamepad = map.gamepad();
+        *map = PlayerInput::player_one_with(&settings, &bindings);
+        if let Some(gamepad) = gamepad {
+            map.set_gamepad(gamepad);
+        }
+    }
+}
+
+fn assign_gamepads(
+    mut events: EventReader<GamepadConnectionEvent>,
+    mut players: Query<&mut InputMap<PlayerInput>>,
+) {
+    for event in events.iter() {
+        match event.connection {
+            GamepadConnection::Connected(_) => {
+                if players
+                    .iter()
+                    .any(|map| map.gamepad() == Some(event.gamepad))
+                {
+                    continue;
+                }
+                if let Some(mut map) = players.iter_mut().find(|map| map.gamepad().is_none()) {
+                    info!("Assigned {:?} to player", event.gamepad);
+                    map.set_gamepad(event.gamepad);
+                }
+            }
+            GamepadConnection::Disconnected => {
+                for mut map in &mut players {
+                    if map.gamepad() == Some(event.gamepad) {
+                        info!("{:?} disconnected", event.gamepad);
+                        map.clear_gamepad();
+                    }
+                }
+            }
+        }
+    }
+}